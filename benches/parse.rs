@@ -0,0 +1,73 @@
+use bee_code::{key, Bencode};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+/// Builds a multi-file torrent with `files` entries, roughly the shape
+/// of what a torrent client loads from disk.
+fn torrent(files: usize) -> Vec<u8> {
+    let files = (0..files)
+        .map(|i| {
+            Bencode::dict([
+                ("length", Bencode::integer(1_048_576 + i as i64 * 7919)),
+                (
+                    "path",
+                    Bencode::list(vec![
+                        Bencode::bytes("disc 1"),
+                        Bencode::bytes(format!("track {:04}.flac", i)),
+                    ]),
+                ),
+            ])
+        })
+        .collect();
+    let info = Bencode::dict([
+        ("files", Bencode::list(files)),
+        ("name", Bencode::bytes("album")),
+        ("piece length", Bencode::integer(262_144)),
+        ("pieces", Bencode::bytes(vec![0xab; 20 * 4096])),
+    ]);
+    let announce_list = Bencode::list(vec![
+        Bencode::list(vec![Bencode::bytes("udp://tracker.example.org:6969")]),
+        Bencode::list(vec![Bencode::bytes("http://backup.example.org/announce")]),
+    ]);
+    return Bencode::dict([
+        (
+            key("announce"),
+            Bencode::bytes("udp://tracker.example.org:6969"),
+        ),
+        (key("announce-list"), announce_list),
+        (key("creation date"), Bencode::integer(1_700_000_000)),
+        (key("info"), info),
+    ])
+    .serialize();
+}
+
+fn bench_parse(c: &mut Criterion) {
+    let small = torrent(1);
+    let large = torrent(2000);
+    let integers = Bencode::list(
+        (0..10_000)
+            .map(|i| Bencode::integer(i * 1_000_003))
+            .collect(),
+    )
+    .serialize();
+
+    c.bench_function("parse single file torrent", |b| {
+        b.iter(|| Bencode::parse_slice(black_box(&small)).unwrap())
+    });
+    c.bench_function("parse 2000 file torrent", |b| {
+        b.iter(|| Bencode::parse_slice(black_box(&large)).unwrap())
+    });
+    c.bench_function("parse 10000 integers", |b| {
+        b.iter(|| Bencode::parse_slice(black_box(&integers)).unwrap())
+    });
+}
+
+fn bench_serialize(c: &mut Criterion) {
+    let large = Bencode::parse(torrent(2000)).unwrap();
+
+    c.bench_function("serialize 2000 file torrent", |b| {
+        b.iter(|| black_box(&large).serialize())
+    });
+}
+
+criterion_group!(benches, bench_parse, bench_serialize);
+criterion_main!(benches);
@@ -0,0 +1,11 @@
+#![no_main]
+
+use bee_code::Bencode;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(value) = Bencode::parse(data.to_vec()) {
+        // Anything that parses must survive a round trip.
+        assert_eq!(Bencode::parse(value.serialize()), Ok(value));
+    }
+});
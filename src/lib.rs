@@ -23,7 +23,25 @@ OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 //! 'bee_code' is a library providing methods for encoding and decoding
 //! bencoded data - a format used in .torrent files
 //! and communication with trackers.
-use std::{collections::BTreeMap, error::Error, fmt::Display};
+//!
+//! The crate is `no_std` compatible when the default `std` feature
+//! is disabled, requiring only `alloc`.
+//!
+//! Enabling the `preserve_order` feature keeps dict entries in the
+//! order they appeared in the input instead of sorting them by key.
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+use alloc::collections::BTreeMap;
+use alloc::{borrow::Cow, boxed::Box, format, string::String, vec, vec::Vec};
+use core::{
+    cmp::Ordering,
+    fmt::Display,
+    hash::{Hash, Hasher},
+    net::{Ipv4Addr, Ipv6Addr, SocketAddrV4, SocketAddrV6},
+    ops::Range,
+};
 
 /// Custom error types returned during parsing
 #[derive(Debug, PartialEq, Eq)]
@@ -39,350 +57,4734 @@ pub enum BencodeError {
     /// Returned when the parsed bytes are not UTF-8.
     /// Includes the position in the vector at which the error occured.
     Utf8Error(String),
+    /// Returned when there is data left in the input after
+    /// the top-level value has been parsed.
+    /// Includes the position in the vector at which the error occured.
+    TrailingData(String),
+    /// Returned when parsing would exceed a configured limit.
+    /// Includes the position in the vector at which the error occured.
+    LimitExceeded(String),
+    /// Returned when the input ends before the current value is complete.
+    /// Includes the position in the vector at which the error occured.
+    UnexpectedEof(String),
+    /// Returned when an operation is applied to a value of the wrong type -
+    /// merging a List into a Dict.
+    TypeMismatch(String),
 }
 
 impl Display for BencodeError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             BencodeError::NegativeLen(e) => write!(f, "{}", e),
             BencodeError::Unexpected(e) => write!(f, "{}", e),
             BencodeError::Utf8Error(e) => write!(f, "{}", e),
+            BencodeError::TrailingData(e) => write!(f, "{}", e),
+            BencodeError::LimitExceeded(e) => write!(f, "{}", e),
+            BencodeError::UnexpectedEof(e) => write!(f, "{}", e),
+            BencodeError::TypeMismatch(e) => write!(f, "{}", e),
         }
     }
 }
 
-impl Error for BencodeError {}
+impl BencodeError {
+    // Rewrites the message while keeping the variant.
+    fn map_message<F: FnOnce(String) -> String>(self, f: F) -> Self {
+        match self {
+            BencodeError::NegativeLen(e) => BencodeError::NegativeLen(f(e)),
+            BencodeError::Unexpected(e) => BencodeError::Unexpected(f(e)),
+            BencodeError::Utf8Error(e) => BencodeError::Utf8Error(f(e)),
+            BencodeError::TrailingData(e) => BencodeError::TrailingData(f(e)),
+            BencodeError::LimitExceeded(e) => BencodeError::LimitExceeded(f(e)),
+            BencodeError::UnexpectedEof(e) => BencodeError::UnexpectedEof(f(e)),
+            BencodeError::TypeMismatch(e) => BencodeError::TypeMismatch(f(e)),
+        }
+    }
 
-/// Represent the four types included in the Bencode specification
-#[derive(Debug, PartialEq, Eq)]
-pub enum Bencode {
-    Bytes(Vec<u8>),
-    Integer(i64),
-    List(Vec<Self>),
-    Dict(BTreeMap<Vec<u8>, Self>),
+    // Names the dict key whose value failed to parse, if it is UTF-8.
+    // Limits apply to the whole input, so those errors are left alone.
+    fn in_value_of(self, key: &[u8]) -> Self {
+        if matches!(self, BencodeError::LimitExceeded(_)) {
+            return self;
+        }
+        match core::str::from_utf8(key) {
+            Ok(key) => return self.map_message(|e| format!("{} in value of key \"{}\"", e, key)),
+            Err(_) => return self,
+        }
+    }
 }
 
-impl Bencode {
-    /// Parses a bytes vector into Bencode type
-    ///
-    /// # Errors
-    ///
-    /// This function will return an error if the input data
-    /// doesn't follow the bencode format specification.
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// use bee_code::Bencode;
-    ///
-    /// let res = Bencode::parse(b"i36e".to_vec());
-    ///
-    /// assert_eq!(
-    ///     res,
-    ///     Ok(Bencode::Integer(36))
-    /// );
-    /// ```
-    pub fn parse(source: Vec<u8>) -> Result<Self, BencodeError> {
-        return Parser::new(&source).decode();
+#[cfg(feature = "std")]
+impl std::error::Error for BencodeError {}
+
+/// Every variant maps to `io::ErrorKind::InvalidData`,
+/// keeping the original error as the source.
+#[cfg(feature = "std")]
+impl From<BencodeError> for std::io::Error {
+    fn from(e: BencodeError) -> Self {
+        return std::io::Error::new(std::io::ErrorKind::InvalidData, e);
     }
+}
 
-    /// Serializes Bencode types to a bytes vector
+/// The deepest nesting of lists and dicts `Bencode::parse` accepts.
+/// Values are parsed recursively, so nesting is capped to keep
+/// hostile inputs from overflowing the stack.
+pub const DEFAULT_MAX_DEPTH: usize = 256;
+
+// Highest depth limit the parser can be configured with
+// while staying within a 2 MiB thread stack, even in debug builds.
+const MAX_SUPPORTED_DEPTH: usize = 384;
+
+/// Options for `Bencode::parse_with_options`, gathering the parser's
+/// limits and strictness checks in one place.
+///
+/// The default matches `Bencode::parse`: dict keys may come in any
+/// order, the last of several equal keys wins, nesting is capped at
+/// `DEFAULT_MAX_DEPTH` and allocation is unlimited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Reject dict keys that are not in strictly ascending order.
+    pub check_canonical: bool,
+    /// Reject dicts containing the same key more than once.
+    pub check_duplicates: bool,
+    /// The deepest nesting of lists and dicts accepted,
+    /// clamped to `Bencode::max_supported_depth`.
+    pub max_depth: usize,
+    /// The most bytes the parsed value may allocate,
+    /// as counted by `Bencode::parse_with_limit`.
+    /// A `PartialDecoder` also uses it to cap how many bytes it
+    /// buffers while waiting for an incomplete value.
+    pub max_bytes: usize,
+    /// The most values, nested ones included, the input may contain.
+    pub max_elements: usize,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        return ParseOptions {
+            check_canonical: false,
+            check_duplicates: false,
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_bytes: usize::MAX,
+            max_elements: usize::MAX,
+        };
+    }
+}
+
+/// Statistics gathered while parsing, as returned by
+/// `Bencode::parse_with_stats`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ParseStats {
+    /// The deepest nesting of lists and dicts reached.
+    /// Zero when the value is an Integer or Bytes.
+    pub max_depth: usize,
+    /// The bytes the parsed value allocates,
+    /// as counted by `Bencode::parse_with_limit`.
+    pub total_bytes: usize,
+    /// The number of values, nested ones included.
+    pub node_count: usize,
+}
+
+#[cfg(not(feature = "preserve_order"))]
+type MapImpl = BTreeMap<Vec<u8>, Bencode>;
+#[cfg(not(feature = "preserve_order"))]
+type IterImpl<'a> = alloc::collections::btree_map::Iter<'a, Vec<u8>, Bencode>;
+#[cfg(not(feature = "preserve_order"))]
+type IterMutImpl<'a> = alloc::collections::btree_map::IterMut<'a, Vec<u8>, Bencode>;
+#[cfg(not(feature = "preserve_order"))]
+type IntoIterImpl = alloc::collections::btree_map::IntoIter<Vec<u8>, Bencode>;
+#[cfg(not(feature = "preserve_order"))]
+type EntryImpl<'a> = alloc::collections::btree_map::Entry<'a, Vec<u8>, Bencode>;
+
+#[cfg(feature = "preserve_order")]
+type MapImpl = indexmap::IndexMap<Vec<u8>, Bencode>;
+#[cfg(feature = "preserve_order")]
+type IterImpl<'a> = indexmap::map::Iter<'a, Vec<u8>, Bencode>;
+#[cfg(feature = "preserve_order")]
+type IterMutImpl<'a> = indexmap::map::IterMut<'a, Vec<u8>, Bencode>;
+#[cfg(feature = "preserve_order")]
+type IntoIterImpl = indexmap::map::IntoIter<Vec<u8>, Bencode>;
+#[cfg(feature = "preserve_order")]
+type EntryImpl<'a> = indexmap::map::Entry<'a, Vec<u8>, Bencode>;
+
+/// The map backing the Dict variant. Its API is the same whatever
+/// features are enabled: entries are sorted by key by default, or kept
+/// in insertion order when the `preserve_order` feature is enabled.
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::BTreeMap;
+/// use bee_code::{Bencode, DictMap};
+///
+/// let mut dict = DictMap::from(BTreeMap::from([(b"spam".to_vec(), Bencode::Integer(1))]));
+/// dict.insert(b"cat".to_vec(), Bencode::Integer(2));
+///
+/// assert_eq!(dict.get(b"spam"), Some(&Bencode::Integer(1)));
+/// assert_eq!(Bencode::Dict(dict).serialize(), b"d3:cati2e4:spami1ee".to_vec());
+/// ```
+#[derive(Default, PartialEq, Eq)]
+pub struct DictMap {
+    map: MapImpl,
+}
+
+impl core::fmt::Debug for DictMap {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        return f.debug_map().entries(self.iter()).finish();
+    }
+}
+
+impl DictMap {
+    /// Creates an empty map.
+    pub fn new() -> DictMap {
+        return DictMap {
+            map: MapImpl::new(),
+        };
+    }
+
+    /// Returns the number of entries.
+    pub fn len(&self) -> usize {
+        return self.map.len();
+    }
+
+    /// Returns `true` if the map has no entries.
+    pub fn is_empty(&self) -> bool {
+        return self.map.is_empty();
+    }
+
+    /// Returns the value stored under `key`.
+    pub fn get(&self, key: &[u8]) -> Option<&Bencode> {
+        return self.map.get(key);
+    }
+
+    /// Returns a mutable reference to the value stored under `key`.
+    pub fn get_mut(&mut self, key: &[u8]) -> Option<&mut Bencode> {
+        return self.map.get_mut(key);
+    }
+
+    /// Checks whether the map holds an entry for `key`.
+    pub fn contains_key(&self, key: &[u8]) -> bool {
+        return self.map.contains_key(key);
+    }
+
+    /// Inserts an entry, returning the value previously stored under `key`.
+    pub fn insert(&mut self, key: Vec<u8>, value: Bencode) -> Option<Bencode> {
+        return self.map.insert(key, value);
+    }
+
+    /// Removes an entry, returning its value.
+    /// The order of the remaining entries is kept.
+    pub fn remove(&mut self, key: &[u8]) -> Option<Bencode> {
+        #[cfg(not(feature = "preserve_order"))]
+        return self.map.remove(key);
+        #[cfg(feature = "preserve_order")]
+        return self.map.shift_remove(key);
+    }
+
+    /// Returns an iterator over the entries in the map's order.
+    pub fn iter(&self) -> DictIter<'_> {
+        return DictIter {
+            iter: self.map.iter(),
+        };
+    }
+
+    /// Returns an iterator over the entries in the map's order,
+    /// with mutable references to the values.
+    pub fn iter_mut(&mut self) -> DictIterMut<'_> {
+        return DictIterMut {
+            iter: self.map.iter_mut(),
+        };
+    }
+
+    /// Returns an iterator over the keys in the map's order.
+    pub fn keys(&self) -> impl Iterator<Item = &Vec<u8>> {
+        return self.map.keys();
+    }
+
+    /// Returns an iterator over the values in the map's order.
+    pub fn values(&self) -> impl Iterator<Item = &Bencode> {
+        return self.map.values();
+    }
+
+    /// Returns an iterator over mutable references to the values
+    /// in the map's order.
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut Bencode> {
+        return self.map.values_mut();
+    }
+
+    /// Returns the entry for `key`, for inserting or updating in place.
     ///
     /// # Examples
     ///
     /// ```
-    /// use bee_code::Bencode;
+    /// use bee_code::{Bencode, DictMap};
     ///
-    /// let int = Bencode::Integer(13);
+    /// let mut dict = DictMap::new();
+    /// dict.entry(b"count".to_vec()).or_insert(Bencode::Integer(0));
+    /// dict.entry(b"count".to_vec())
+    ///     .and_modify(|count| *count = Bencode::Integer(1))
+    ///     .or_insert(Bencode::Integer(0));
     ///
-    /// assert_eq!(
-    ///     int.serialize(),
-    ///     vec![105, 49, 51, 101]
-    /// );
+    /// assert_eq!(dict.get(b"count"), Some(&Bencode::Integer(1)));
     /// ```
-    pub fn serialize(&self) -> Vec<u8> {
-        match self {
-            Bencode::Integer(num) => {
-                return format!("i{}e", num).as_bytes().to_vec();
-            }
-            Bencode::List(list) => {
-                let mut temp = b"l".to_vec();
-                for item in list {
-                    temp.extend(item.serialize());
-                }
-                temp.push(b'e');
-                return temp;
-            }
-            Bencode::Dict(dict) => {
-                let mut temp = b"d".to_vec();
-                for (key, value) in dict {
-                    temp.extend(Self::serialize_bytes(key));
-                    temp.extend(value.serialize());
-                }
-                temp.push(b'e');
-                return temp;
-            }
-            Bencode::Bytes(bytes) => {
-                return Self::serialize_bytes(bytes);
-            }
-        }
+    pub fn entry(&mut self, key: Vec<u8>) -> DictEntry<'_> {
+        return DictEntry {
+            entry: self.map.entry(key),
+        };
+    }
+
+    /// Keeps only the entries for which `keep` returns `true`.
+    /// The order of the remaining entries is kept.
+    pub fn retain<F>(&mut self, keep: F)
+    where
+        F: FnMut(&Vec<u8>, &mut Bencode) -> bool,
+    {
+        self.map.retain(keep);
     }
 
-    fn serialize_bytes(bytes: &[u8]) -> Vec<u8> {
-        let temp = format!("{}:", bytes.len());
-        let mut temp = temp.as_bytes().to_vec();
-        temp.extend(bytes);
-        return temp;
+    // Entries sorted by key, the order they are serialized in.
+    // A BTreeMap already iterates in that order, so nothing is collected.
+    #[cfg(not(feature = "preserve_order"))]
+    fn sorted_iter(&self) -> impl DoubleEndedIterator<Item = (&[u8], &Bencode)> {
+        return self.map.iter().map(|(k, v)| (k.as_slice(), v));
+    }
+
+    #[cfg(feature = "preserve_order")]
+    fn sorted_iter(&self) -> impl DoubleEndedIterator<Item = (&[u8], &Bencode)> {
+        return self.map.sorted_entries().into_iter();
     }
 }
 
-struct Parser<'a> {
-    pos: usize,
-    input: &'a [u8],
+/// Iterator over the entries of a `DictMap`, returned by `DictMap::iter`.
+pub struct DictIter<'a> {
+    iter: IterImpl<'a>,
 }
 
-impl Parser<'_> {
-    fn new(bytes: &[u8]) -> Parser {
-        return Parser {
-            input: bytes,
-            pos: 0,
-        };
+impl<'a> Iterator for DictIter<'a> {
+    type Item = (&'a Vec<u8>, &'a Bencode);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        return self.iter.next();
     }
 
-    fn decode(&mut self) -> Result<Bencode, BencodeError> {
-        return self.parse_element();
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        return self.iter.size_hint();
+    }
+}
+
+/// Iterator over the entries of a `DictMap` with mutable references
+/// to the values, returned by `DictMap::iter_mut`.
+pub struct DictIterMut<'a> {
+    iter: IterMutImpl<'a>,
+}
+
+impl<'a> Iterator for DictIterMut<'a> {
+    type Item = (&'a Vec<u8>, &'a mut Bencode);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        return self.iter.next();
     }
 
-    fn next(&self) -> u8 {
-        return self.input.get(self.pos).unwrap().clone();
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        return self.iter.size_hint();
     }
+}
 
-    fn eof(&self) -> bool {
-        return self.pos >= self.input.len();
+/// A view into a single entry of a `DictMap`, returned by `DictMap::entry`.
+pub struct DictEntry<'a> {
+    entry: EntryImpl<'a>,
+}
+
+impl<'a> DictEntry<'a> {
+    /// Returns the key of the entry.
+    pub fn key(&self) -> &Vec<u8> {
+        return self.entry.key();
     }
 
-    fn consume(&mut self) -> u8 {
-        let c = self.input.get(self.pos);
-        self.pos += 1;
-        return c.unwrap().clone();
+    /// Inserts `default` if the entry is vacant,
+    /// returning a mutable reference to the value.
+    pub fn or_insert(self, default: Bencode) -> &'a mut Bencode {
+        return self.entry.or_insert(default);
     }
 
-    fn consume_while<F>(&mut self, test: F) -> Vec<u8>
-    where
-        F: Fn(u8) -> bool,
-    {
-        let mut res = vec![];
-        while !self.eof() && test(self.next()) {
-            res.push(self.consume());
-        }
-        return res;
+    /// Inserts the result of `default` if the entry is vacant,
+    /// returning a mutable reference to the value.
+    pub fn or_insert_with<F: FnOnce() -> Bencode>(self, default: F) -> &'a mut Bencode {
+        return self.entry.or_insert_with(default);
     }
 
-    fn consume_expected(&mut self, expected: u8) -> Result<u8, BencodeError> {
-        match self.next() {
-            c if c == expected => Ok(self.consume()),
-            _ => Err(BencodeError::Unexpected(format!(
-                "Unexpected character at index {}. Expected {} found {}",
-                self.pos,
-                expected,
-                self.input[self.pos + 1]
-            ))),
-        }
+    /// Calls `modify` on the value if the entry is occupied.
+    pub fn and_modify<F: FnOnce(&mut Bencode)>(self, modify: F) -> DictEntry<'a> {
+        return DictEntry {
+            entry: self.entry.and_modify(modify),
+        };
     }
+}
 
-    fn parse_dict(&mut self) -> Result<Bencode, BencodeError> {
-        self.consume_expected(b'd')?;
-        let mut dict = BTreeMap::new();
+impl<'a> IntoIterator for &'a mut DictMap {
+    type Item = (&'a Vec<u8>, &'a mut Bencode);
+    type IntoIter = DictIterMut<'a>;
 
-        while self.next() != b'e' {
-            let k = self.parse_string()?;
-            let v = self.parse_element()?;
-            dict.insert(k, v);
-        }
-        self.consume_expected(b'e')?;
-        return Ok(Bencode::Dict(dict));
+    fn into_iter(self) -> Self::IntoIter {
+        return self.iter_mut();
     }
+}
 
-    fn parse_list(&mut self) -> Result<Bencode, BencodeError> {
-        self.consume_expected(b'l')?;
-        let mut list = vec![];
-        while self.next() != b'e' {
-            list.push(self.parse_element()?);
-        }
-        self.consume_expected(b'e')?;
-        return Ok(Bencode::List(list));
+/// Owning iterator over the entries of a `DictMap`.
+pub struct DictIntoIter {
+    iter: IntoIterImpl,
+}
+
+impl Iterator for DictIntoIter {
+    type Item = (Vec<u8>, Bencode);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        return self.iter.next();
     }
 
-    fn parse_element(&mut self) -> Result<Bencode, BencodeError> {
-        match self.next() {
-            b'd' => self.parse_dict(),
-            b'l' => self.parse_list(),
-            b'i' => self.parse_int(),
-            b'0'..=b'9' => Ok(Bencode::Bytes(self.parse_string()?)),
-            _ => Err(BencodeError::Unexpected(format!(
-                "Unexpected value type at index {}",
-                self.pos
-            ))),
-        }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        return self.iter.size_hint();
     }
+}
 
-    fn parse_int(&mut self) -> Result<Bencode, BencodeError> {
-        let pos = self.pos;
-        self.consume_expected(b'i')?;
-        let mut sign = 1;
-        match self.consume_expected(b'-') {
-            Ok(_) => sign = -1,
-            Err(_) => {}
-        }
-        let v = self.consume_while(|c| c != b'e');
-        if v.len() > 1 && v[0] == b'0' {
-            return Err(BencodeError::Unexpected(format!(
-                "Leading 0 while parsing integer at index {}",
-                pos
-            )));
-        }
-        if v.len() == 1 && v[0] == b'0' && sign == -1 {
-            return Err(BencodeError::Unexpected(format!(
-                "Negative 0 while parsing integer at index {}",
-                pos
-            )));
-        }
-        let int: i64 = match std::str::from_utf8(&v) {
-            Ok(value) => value
-                .parse()
-                .expect("Integer should only include numeric values"),
-            Err(e) => {
-                return Err(BencodeError::Utf8Error(format!(
-                    "Non UTF8 encoded integer value at index {}. {}",
-                    pos, e
-                )))
-            }
-        };
-        self.consume_expected(b'e')?;
-        return Ok(Bencode::Integer(int * sign));
+impl<'a> IntoIterator for &'a DictMap {
+    type Item = (&'a Vec<u8>, &'a Bencode);
+    type IntoIter = DictIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        return self.iter();
     }
+}
 
-    fn parse_string(&mut self) -> Result<Vec<u8>, BencodeError> {
-        let len = self.parse_len()?;
-        self.consume_expected(b':')?;
-        let mut bytes = vec![];
-        for _ in 0..len {
-            bytes.push(self.consume());
-        }
-        return Ok(bytes);
+impl IntoIterator for DictMap {
+    type Item = (Vec<u8>, Bencode);
+    type IntoIter = DictIntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        return DictIntoIter {
+            iter: self.map.into_iter(),
+        };
     }
+}
 
-    fn parse_len(&mut self) -> Result<usize, BencodeError> {
-        if self.next() == b'-' {
-            return Err(BencodeError::NegativeLen(format!(
-                "Negative string len at index {}",
-                self.pos,
-            )));
-        }
-        let v = self.consume_while(|c| c != b':');
-        let len: usize = match std::str::from_utf8(&v) {
-            Ok(value) => value
-                .parse()
-                .expect("String length should include only numbers"),
-            Err(e) => {
-                return Err(BencodeError::Utf8Error(format!(
-                    "Non UTF8 encoded string length at index {}. {}",
-                    self.pos, e
-                )))
-            }
+impl FromIterator<(Vec<u8>, Bencode)> for DictMap {
+    fn from_iter<I: IntoIterator<Item = (Vec<u8>, Bencode)>>(iter: I) -> Self {
+        return DictMap {
+            map: iter.into_iter().collect(),
         };
-        return Ok(len);
     }
 }
 
-#[cfg(test)]
-mod test {
-    use std::collections::BTreeMap;
+impl Extend<(Vec<u8>, Bencode)> for DictMap {
+    fn extend<I: IntoIterator<Item = (Vec<u8>, Bencode)>>(&mut self, iter: I) {
+        self.map.extend(iter);
+    }
+}
 
-    use crate::{Bencode, Parser};
+impl From<BTreeMap<Vec<u8>, Bencode>> for DictMap {
+    fn from(map: BTreeMap<Vec<u8>, Bencode>) -> Self {
+        return map.into_iter().collect();
+    }
+}
 
-    #[test]
-    fn test_parse_string() {
-        let mut p = Parser::new(b"6:string");
-        assert_eq!(p.parse_string(), Ok(b"string".to_vec()));
+impl<const N: usize> From<[(Vec<u8>, Bencode); N]> for DictMap {
+    fn from(entries: [(Vec<u8>, Bencode); N]) -> Self {
+        return entries.into_iter().collect();
     }
-    #[test]
-    fn test_parse_string_empty() {
-        let mut p = Parser::new(b"0:");
-        assert_eq!(p.parse_string(), Ok(b"".to_vec()));
+}
+
+impl From<DictMap> for BTreeMap<Vec<u8>, Bencode> {
+    fn from(dict: DictMap) -> Self {
+        return dict.into_iter().collect();
     }
-    #[test]
-    fn test_parse_string_with_neg_len() {
-        let mut p = Parser::new(b"-2:text");
-        assert_eq!(
-            p.parse_string(),
-            Err(crate::BencodeError::NegativeLen(
-                "Negative string len at index 0".to_owned(),
-            ))
-        );
+}
+
+/// A map that dict entries can be parsed into with `Bencode::parse_into`
+/// and serialized from with `Bencode::serialize_map`.
+///
+/// Implemented for `DictMap`, `BTreeMap`, `HashMap` (with `std`), `IndexMap`
+/// (with `preserve_order`) and a `Vec` of pairs kept in insertion order.
+/// The `Vec` keeps every entry, including duplicate keys, but only the
+/// last entry for each key is serialized, as if it had been a map.
+pub trait BencodeMap: Default {
+    /// Inserts an entry. Maps replace the value of an existing equal key,
+    /// while a `Vec` of pairs appends it.
+    fn insert_entry(&mut self, key: Vec<u8>, value: Bencode);
+
+    /// Returns the entries in the map's own iteration order.
+    fn entries_iter(&self) -> Box<dyn Iterator<Item = (&[u8], &Bencode)> + '_>;
+
+    /// Checks whether the map holds an entry for `key`.
+    fn contains_entry(&self, key: &[u8]) -> bool {
+        return self.entries_iter().any(|(k, _)| k == key);
     }
-    #[test]
-    fn test_parse_int() {
-        let mut p = Parser::new(b"i13e");
-        assert_eq!(p.parse_int(), Ok(Bencode::Integer(13)));
+
+    /// Returns the entries sorted by key, the order they are serialized in.
+    fn sorted_entries(&self) -> Vec<(&[u8], &Bencode)> {
+        let mut entries: Vec<_> = self.entries_iter().collect();
+        entries.sort_by(|a, b| Bencode::key_cmp(a.0, b.0));
+        return entries;
     }
-    #[test]
-    fn test_parse_int_neg() {
-        let mut p = Parser::new(b"i-13e");
-        assert_eq!(p.parse_int(), Ok(Bencode::Integer(-13)));
+}
+
+impl BencodeMap for DictMap {
+    fn insert_entry(&mut self, key: Vec<u8>, value: Bencode) {
+        self.insert(key, value);
     }
-    #[test]
-    fn test_parse_int_neg_zero() {
-        let mut p = Parser::new(b"i-0e");
-        assert_eq!(
-            p.parse_int(),
-            Err(crate::BencodeError::Unexpected(
-                "Negative 0 while parsing integer at index 0".to_owned()
-            ))
-        );
+
+    fn entries_iter(&self) -> Box<dyn Iterator<Item = (&[u8], &Bencode)> + '_> {
+        return Box::new(self.iter().map(|(k, v)| (k.as_slice(), v)));
     }
-    #[test]
-    fn test_parse_int_lead_zero() {
-        let mut p = Parser::new(b"i0934e");
-        assert_eq!(
-            p.parse_int(),
-            Err(crate::BencodeError::Unexpected(
+
+    fn contains_entry(&self, key: &[u8]) -> bool {
+        return self.contains_key(key);
+    }
+
+    fn sorted_entries(&self) -> Vec<(&[u8], &Bencode)> {
+        return self.map.sorted_entries();
+    }
+}
+
+impl BencodeMap for BTreeMap<Vec<u8>, Bencode> {
+    fn insert_entry(&mut self, key: Vec<u8>, value: Bencode) {
+        self.insert(key, value);
+    }
+
+    fn entries_iter(&self) -> Box<dyn Iterator<Item = (&[u8], &Bencode)> + '_> {
+        return Box::new(self.iter().map(|(k, v)| (k.as_slice(), v)));
+    }
+
+    fn contains_entry(&self, key: &[u8]) -> bool {
+        return self.contains_key(key);
+    }
+
+    fn sorted_entries(&self) -> Vec<(&[u8], &Bencode)> {
+        return self.entries_iter().collect();
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S: core::hash::BuildHasher + Default> BencodeMap
+    for std::collections::HashMap<Vec<u8>, Bencode, S>
+{
+    fn insert_entry(&mut self, key: Vec<u8>, value: Bencode) {
+        self.insert(key, value);
+    }
+
+    fn entries_iter(&self) -> Box<dyn Iterator<Item = (&[u8], &Bencode)> + '_> {
+        return Box::new(self.iter().map(|(k, v)| (k.as_slice(), v)));
+    }
+
+    fn contains_entry(&self, key: &[u8]) -> bool {
+        return self.contains_key(key);
+    }
+}
+
+#[cfg(feature = "preserve_order")]
+impl BencodeMap for indexmap::IndexMap<Vec<u8>, Bencode> {
+    fn insert_entry(&mut self, key: Vec<u8>, value: Bencode) {
+        self.insert(key, value);
+    }
+
+    fn entries_iter(&self) -> Box<dyn Iterator<Item = (&[u8], &Bencode)> + '_> {
+        return Box::new(self.iter().map(|(k, v)| (k.as_slice(), v)));
+    }
+
+    fn contains_entry(&self, key: &[u8]) -> bool {
+        return self.contains_key(key);
+    }
+}
+
+impl BencodeMap for Vec<(Vec<u8>, Bencode)> {
+    fn insert_entry(&mut self, key: Vec<u8>, value: Bencode) {
+        self.push((key, value));
+    }
+
+    fn entries_iter(&self) -> Box<dyn Iterator<Item = (&[u8], &Bencode)> + '_> {
+        return Box::new(self.iter().map(|(k, v)| (k.as_slice(), v)));
+    }
+
+    fn sorted_entries(&self) -> Vec<(&[u8], &Bencode)> {
+        let mut entries: Vec<_> = self.entries_iter().collect();
+        entries.sort_by(|a, b| Bencode::key_cmp(a.0, b.0));
+        // The sort is stable, so the last entry of each run of equal keys
+        // is the one inserted last. Keep it, like a map would.
+        entries.dedup_by(|later, kept| {
+            if later.0 != kept.0 {
+                return false;
+            }
+            core::mem::swap(later, kept);
+            return true;
+        });
+        return entries;
+    }
+}
+
+/// Keys of a top-level Dict paired with the byte range
+/// their values occupied in the parsed input.
+pub type KeySpans = Vec<(Vec<u8>, Range<usize>)>;
+
+/// Represent the four types included in the Bencode specification
+///
+/// Equal values hash equally, so `Bencode` can be used as a key
+/// in a `HashMap` or stored in a `HashSet`.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Bencode {
+    Bytes(Vec<u8>),
+    Integer(i64),
+    List(Vec<Self>),
+    Dict(DictMap),
+}
+
+/// Converts a string into a dict key.
+/// Keys made this way sort identically to the equivalent byte literals.
+///
+/// # Examples
+///
+/// ```
+/// use bee_code::key;
+///
+/// assert_eq!(key("info"), b"info".to_vec());
+/// ```
+pub fn key(s: &str) -> Vec<u8> {
+    return s.as_bytes().to_vec();
+}
+
+/// Callbacks for walking a Bencode tree with `Bencode::visit`.
+/// Every method does nothing by default, so implementors only
+/// need to override the ones they care about.
+pub trait Visitor {
+    /// Called for every Integer.
+    fn visit_integer(&mut self, _i: i64) {}
+    /// Called for every Bytes value. Not called for dict keys.
+    fn visit_bytes(&mut self, _b: &[u8]) {}
+    /// Called before the items of a List are visited.
+    fn enter_list(&mut self) {}
+    /// Called after the items of a List are visited.
+    fn leave_list(&mut self) {}
+    /// Called before the entries of a Dict are visited.
+    fn enter_dict(&mut self) {}
+    /// Called with each Dict key, before its value is visited.
+    fn visit_key(&mut self, _key: &[u8]) {}
+    /// Called after the entries of a Dict are visited.
+    fn leave_dict(&mut self) {}
+}
+
+/// A parsed value paired with the byte range it occupied in the input,
+/// as returned by `Bencode::parse_spanned`. The span of every nested
+/// value lies within the span of its parent.
+#[derive(Debug, PartialEq, Eq)]
+pub struct SpannedBencode {
+    /// The parsed value.
+    pub value: SpannedValue,
+    /// The byte range the value occupied in the input.
+    pub span: Range<usize>,
+}
+
+/// The contents of a `SpannedBencode`, mirroring the variants of `Bencode`.
+/// Dict entries are kept in the order they appeared in the input.
+#[derive(Debug, PartialEq, Eq)]
+pub enum SpannedValue {
+    Bytes(Vec<u8>),
+    Integer(i64),
+    List(Vec<SpannedBencode>),
+    Dict(Vec<(Vec<u8>, SpannedBencode)>),
+}
+
+/// Number of values of each variant in a tree,
+/// as returned by `Bencode::variant_counts`.
+/// Dict keys are not values and are not counted.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct VariantCounts {
+    /// Number of Bytes values.
+    pub bytes: usize,
+    /// Number of Integer values.
+    pub integers: usize,
+    /// Number of List values.
+    pub lists: usize,
+    /// Number of Dict values.
+    pub dicts: usize,
+}
+
+impl Visitor for VariantCounts {
+    fn visit_integer(&mut self, _i: i64) {
+        self.integers += 1;
+    }
+
+    fn visit_bytes(&mut self, _b: &[u8]) {
+        self.bytes += 1;
+    }
+
+    fn enter_list(&mut self) {
+        self.lists += 1;
+    }
+
+    fn enter_dict(&mut self) {
+        self.dicts += 1;
+    }
+}
+
+impl Hash for Bencode {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        core::mem::discriminant(self).hash(state);
+        match self {
+            Bencode::Bytes(bytes) => bytes.hash(state),
+            Bencode::Integer(num) => num.hash(state),
+            Bencode::List(list) => list.hash(state),
+            Bencode::Dict(dict) => {
+                state.write_usize(dict.len());
+                for (key, value) in dict.sorted_iter() {
+                    key.hash(state);
+                    value.hash(state);
+                }
+            }
+        }
+    }
+}
+
+/// The default value is an empty Dict, serialized as `de`,
+/// ready to be filled in as the root of a torrent or message.
+impl Default for Bencode {
+    fn default() -> Self {
+        return Bencode::Dict(DictMap::new());
+    }
+}
+
+/// Parses a bytes vector with `Bencode::parse`.
+impl TryFrom<Vec<u8>> for Bencode {
+    type Error = BencodeError;
+
+    fn try_from(source: Vec<u8>) -> Result<Self, Self::Error> {
+        return Bencode::parse(source);
+    }
+}
+
+/// Parses a bytes slice with `Bencode::parse_slice`.
+impl TryFrom<&[u8]> for Bencode {
+    type Error = BencodeError;
+
+    fn try_from(source: &[u8]) -> Result<Self, Self::Error> {
+        return Bencode::parse_slice(source);
+    }
+}
+
+// Number of decimal digits needed to write `num`.
+fn digit_count(mut num: u64) -> usize {
+    let mut count = 1;
+    while num >= 10 {
+        num /= 10;
+        count += 1;
+    }
+    return count;
+}
+
+/// Accumulates ASCII digits into a `u64`. Returns `None` if `digits`
+/// is empty, holds anything but `0-9`, or overflows.
+fn parse_digits(digits: &[u8]) -> Option<u64> {
+    if digits.is_empty() {
+        return None;
+    }
+    let mut value: u64 = 0;
+    for &c in digits {
+        if !c.is_ascii_digit() {
+            return None;
+        }
+        value = value.checked_mul(10)?.checked_add(u64::from(c - b'0'))?;
+    }
+    return Some(value);
+}
+
+fn bytes_serialized_len(bytes: &[u8]) -> usize {
+    return digit_count(bytes.len() as u64) + 1 + bytes.len();
+}
+
+impl Bencode {
+    /// Parses a bytes vector into Bencode type
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the input data
+    /// doesn't follow the bencode format specification.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::Bencode;
+    ///
+    /// let res = Bencode::parse(b"i36e".to_vec());
+    ///
+    /// assert_eq!(
+    ///     res,
+    ///     Ok(Bencode::Integer(36))
+    /// );
+    /// ```
+    pub fn parse(source: Vec<u8>) -> Result<Self, BencodeError> {
+        return Self::parse_slice(&source);
+    }
+
+    /// Parses a bytes slice into Bencode type
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the input data
+    /// doesn't follow the bencode format specification.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::Bencode;
+    ///
+    /// let res = Bencode::parse_slice(b"i36e");
+    ///
+    /// assert_eq!(res, Ok(Bencode::Integer(36)));
+    /// ```
+    pub fn parse_slice(source: &[u8]) -> Result<Self, BencodeError> {
+        return Parser::new(source).decode_complete();
+    }
+
+    /// Parses a bytes vector into Bencode type, limiting the total
+    /// number of bytes the parsed value may allocate. Every value
+    /// counts its in-memory size plus the length of its byte strings.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the input data
+    /// doesn't follow the bencode format specification or
+    /// the value would allocate more than `max_bytes`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::{Bencode, BencodeError};
+    ///
+    /// let res = Bencode::parse_with_limit(b"4:spam".to_vec(), 2);
+    ///
+    /// assert!(matches!(res, Err(BencodeError::LimitExceeded(_))));
+    /// ```
+    pub fn parse_with_limit(source: Vec<u8>, max_bytes: usize) -> Result<Self, BencodeError> {
+        let mut parser = Parser::new(&source);
+        parser.max_bytes = max_bytes;
+        return parser.decode_complete();
+    }
+
+    /// Parses a bytes vector into Bencode type, limiting the total
+    /// number of values, nested ones included, it may contain.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the input data
+    /// doesn't follow the bencode format specification or
+    /// contains more than `max_elements` values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::{Bencode, BencodeError};
+    ///
+    /// let res = Bencode::parse_with_element_limit(b"li1ei2ee".to_vec(), 2);
+    ///
+    /// assert!(matches!(res, Err(BencodeError::LimitExceeded(_))));
+    /// ```
+    pub fn parse_with_element_limit(
+        source: Vec<u8>,
+        max_elements: usize,
+    ) -> Result<Self, BencodeError> {
+        let mut parser = Parser::new(&source);
+        parser.max_elements = max_elements;
+        return parser.decode_complete();
+    }
+
+    /// Parses a bytes vector into Bencode type with the limits and
+    /// checks set in `options`. Disabling the checks skips work
+    /// for input already known to be well formed.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the input data
+    /// doesn't follow the bencode format specification or
+    /// breaks one of the enabled checks or limits.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::{Bencode, ParseOptions};
+    ///
+    /// let strict = ParseOptions {
+    ///     check_canonical: true,
+    ///     ..ParseOptions::default()
+    /// };
+    ///
+    /// assert!(Bencode::parse_with_options(b"d4:spami1e3:cati2ee".to_vec(), strict).is_err());
+    /// ```
+    pub fn parse_with_options(
+        source: Vec<u8>,
+        options: ParseOptions,
+    ) -> Result<Self, BencodeError> {
+        return Parser::with_options(&source, options).decode_complete();
+    }
+
+    /// Parses a bytes slice into Bencode type, also returning the
+    /// byte range each value of a top-level Dict occupied in the input.
+    /// This allows hashing the exact original bytes of a value, such as
+    /// the `info` dict of a torrent, even if the input isn't canonical.
+    /// The ranges are empty if the top-level value is not a Dict.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the input data
+    /// doesn't follow the bencode format specification, or if the
+    /// top-level Dict repeats a key, since a range could then point
+    /// at bytes that are not part of the returned value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::Bencode;
+    ///
+    /// let source = b"d4:infod6:lengthi3eee";
+    /// let (_, spans) = Bencode::parse_with_spans(source).unwrap();
+    ///
+    /// assert_eq!(spans[0].0, b"info".to_vec());
+    /// assert_eq!(&source[spans[0].1.clone()], b"d6:lengthi3ee");
+    /// ```
+    pub fn parse_with_spans(source: &[u8]) -> Result<(Self, KeySpans), BencodeError> {
+        let mut parser = Parser::new(source);
+        parser.spans = Some(vec![]);
+        let value = parser.decode_complete()?;
+        return Ok((value, parser.spans.unwrap_or_default()));
+    }
+
+    /// Parses a bytes slice into Bencode type, also returning how deep
+    /// the value nests, how much it allocates and how many values it holds.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the input data
+    /// doesn't follow the bencode format specification.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::Bencode;
+    ///
+    /// let (_, stats) = Bencode::parse_with_stats(b"lli1eee").unwrap();
+    ///
+    /// assert_eq!(stats.max_depth, 2);
+    /// assert_eq!(stats.node_count, 3);
+    /// ```
+    pub fn parse_with_stats(source: &[u8]) -> Result<(Self, ParseStats), BencodeError> {
+        let mut parser = Parser::new(source);
+        let value = parser.decode_complete()?;
+        let stats = ParseStats {
+            max_depth: parser.deepest,
+            total_bytes: parser.allocated,
+            node_count: parser.elements,
+        };
+        return Ok((value, stats));
+    }
+
+    /// Parses a bytes slice into a tree recording the byte range
+    /// every value occupied in the input, for tools that need to map
+    /// values back to their source bytes.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the input data
+    /// doesn't follow the bencode format specification.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::{Bencode, SpannedValue};
+    ///
+    /// let tree = Bencode::parse_spanned(b"li1e4:spame").unwrap();
+    ///
+    /// assert_eq!(tree.span, 0..11);
+    /// match tree.value {
+    ///     SpannedValue::List(items) => assert_eq!(items[1].span, 4..10),
+    ///     _ => unreachable!(),
+    /// }
+    /// ```
+    pub fn parse_spanned(source: &[u8]) -> Result<SpannedBencode, BencodeError> {
+        let mut parser = Parser::new(source);
+        parser.spanned = Some(vec![]);
+        let mut value = parser.decode_complete()?;
+        let children = parser.spanned.unwrap_or_default();
+        return Ok(Parser::spanned(&mut value, children, 0..source.len()));
+    }
+
+    /// Parses a bytes slice into Bencode type, collecting every error
+    /// instead of stopping at the first one. When an element of a List
+    /// or an entry of a Dict fails to parse, the error is recorded and
+    /// parsing resumes at the next byte that could start a new element.
+    ///
+    /// Recovery is best-effort: a single malformed element can cause
+    /// follow-up errors, and running out of input or exceeding a limit
+    /// ends parsing. The value is `None` if nothing could be recovered.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::Bencode;
+    ///
+    /// let (value, errors) = Bencode::parse_collecting(b"li1ex4:spame");
+    ///
+    /// assert_eq!(errors.len(), 1);
+    /// assert_eq!(
+    ///     value,
+    ///     Some(Bencode::List(vec![
+    ///         Bencode::Integer(1),
+    ///         Bencode::Bytes(b"spam".to_vec()),
+    ///     ]))
+    /// );
+    /// ```
+    pub fn parse_collecting(source: &[u8]) -> (Option<Self>, Vec<BencodeError>) {
+        let mut parser = Parser::new(source);
+        parser.errors = Some(vec![]);
+        let res = parser.decode();
+        let mut errors = parser.errors.take().unwrap_or_default();
+        match res {
+            Ok(value) => {
+                if !parser.eof() {
+                    errors.push(parser.trailing_error());
+                }
+                return (Some(value), errors);
+            }
+            Err(e) => {
+                errors.push(e);
+                return (None, errors);
+            }
+        }
+    }
+
+    /// Parses a bytes slice holding a Dict into any `BencodeMap`,
+    /// such as a `HashMap` for faster lookups. Nested dicts use the
+    /// default `DictMap`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the input data
+    /// doesn't follow the bencode format specification or
+    /// the top-level value is not a Dict.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "std")] {
+    /// use std::collections::HashMap;
+    /// use bee_code::Bencode;
+    ///
+    /// let map: HashMap<Vec<u8>, Bencode> = Bencode::parse_into(b"d4:spami1ee").unwrap();
+    ///
+    /// assert_eq!(map.get(b"spam".as_slice()), Some(&Bencode::Integer(1)));
+    /// # }
+    /// ```
+    pub fn parse_into<M: BencodeMap>(source: &[u8]) -> Result<M, BencodeError> {
+        let mut parser = Parser::new(source);
+        if parser.next()? != b'd' {
+            return Err(BencodeError::TypeMismatch(format!(
+                "Expected a Dict at index {}",
+                parser.pos
+            )));
+        }
+        let map = parser.parse_map()?;
+        if !parser.eof() {
+            return Err(parser.trailing_error());
+        }
+        return Ok(map);
+    }
+
+    /// Serializes the entries of any `BencodeMap` as a Dict,
+    /// sorting them by key regardless of the map's own order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::Bencode;
+    ///
+    /// let pairs = vec![
+    ///     (b"spam".to_vec(), Bencode::Integer(1)),
+    ///     (b"cat".to_vec(), Bencode::Integer(2)),
+    /// ];
+    ///
+    /// assert_eq!(Bencode::serialize_map(&pairs), b"d3:cati2e4:spami1ee".to_vec());
+    /// ```
+    pub fn serialize_map<M: BencodeMap>(map: &M) -> Vec<u8> {
+        let entries = map.sorted_entries();
+        let len = 2 + entries
+            .iter()
+            .map(|(key, value)| bytes_serialized_len(key) + value.serialized_len())
+            .sum::<usize>();
+        let mut out = Vec::with_capacity(len);
+        Self::serialize_entries(entries, &mut out);
+        return out;
+    }
+
+    /// Serializes entries that are already sorted by key as a Dict,
+    /// without collecting them into a map first.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the keys are not
+    /// in strictly ascending order, including duplicate keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::Bencode;
+    ///
+    /// let one = Bencode::Integer(1);
+    /// let two = Bencode::Integer(2);
+    /// let entries = [(b"cat".as_slice(), &one), (b"spam".as_slice(), &two)];
+    ///
+    /// assert_eq!(
+    ///     Bencode::serialize_dict_from(entries),
+    ///     Ok(b"d3:cati1e4:spami2ee".to_vec())
+    /// );
+    /// ```
+    pub fn serialize_dict_from<'a, I>(entries: I) -> Result<Vec<u8>, BencodeError>
+    where
+        I: IntoIterator<Item = (&'a [u8], &'a Bencode)>,
+    {
+        let mut out = vec![b'd'];
+        let mut last: Option<&[u8]> = None;
+        for (index, (key, value)) in entries.into_iter().enumerate() {
+            if let Some(last) = last {
+                match Self::key_cmp(last, key) {
+                    Ordering::Less => {}
+                    Ordering::Equal => {
+                        return Err(BencodeError::Unexpected(format!(
+                            "Duplicate dict key \"{}\" at entry {}",
+                            String::from_utf8_lossy(key),
+                            index
+                        )))
+                    }
+                    Ordering::Greater => {
+                        return Err(BencodeError::Unexpected(format!(
+                            "Dict key \"{}\" out of order at entry {}",
+                            String::from_utf8_lossy(key),
+                            index
+                        )))
+                    }
+                }
+            }
+            Self::serialize_bytes(key, &mut out);
+            value.serialize_into(&mut out);
+            last = Some(key);
+        }
+        out.push(b'e');
+        return Ok(out);
+    }
+
+    /// Returns the deepest nesting the parser can safely be configured
+    /// to accept. `DEFAULT_MAX_DEPTH` is well below this ceiling.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::{Bencode, DEFAULT_MAX_DEPTH};
+    ///
+    /// assert!(DEFAULT_MAX_DEPTH <= Bencode::max_supported_depth());
+    /// ```
+    pub const fn max_supported_depth() -> usize {
+        return MAX_SUPPORTED_DEPTH;
+    }
+
+    /// Parses a single Bencode value from the start of a bytes slice.
+    /// Returns the value and the number of bytes it occupied,
+    /// leaving any data after it untouched.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the leading data
+    /// doesn't follow the bencode format specification.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::Bencode;
+    ///
+    /// let res = Bencode::parse_prefix(b"i36e4:spam");
+    ///
+    /// assert_eq!(
+    ///     res,
+    ///     Ok((Bencode::Integer(36), 4))
+    /// );
+    /// ```
+    pub fn parse_prefix(source: &[u8]) -> Result<(Self, usize), BencodeError> {
+        let mut parser = Parser::new(source);
+        let value = parser.decode()?;
+        return Ok((value, parser.pos));
+    }
+
+    /// Serializes Bencode types to a bytes vector
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::Bencode;
+    ///
+    /// let int = Bencode::Integer(13);
+    ///
+    /// assert_eq!(
+    ///     int.serialize(),
+    ///     vec![105, 49, 51, 101]
+    /// );
+    /// ```
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.serialized_len());
+        self.serialize_into(&mut out);
+        return out;
+    }
+
+    /// Serializes Bencode types straight into a writer,
+    /// without building the whole encoding in memory first.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if writing fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::Bencode;
+    ///
+    /// let list = Bencode::List(vec![Bencode::Integer(1)]);
+    /// let mut out = vec![];
+    /// list.serialize_to(&mut out).unwrap();
+    ///
+    /// assert_eq!(out, list.serialize());
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn serialize_to<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        match self {
+            Bencode::Integer(num) => write!(writer, "i{}e", num)?,
+            Bencode::List(list) => {
+                writer.write_all(b"l")?;
+                for item in list {
+                    item.serialize_to(writer)?;
+                }
+                writer.write_all(b"e")?;
+            }
+            Bencode::Dict(dict) => {
+                writer.write_all(b"d")?;
+                for (key, value) in dict.sorted_iter() {
+                    write!(writer, "{}:", key.len())?;
+                    writer.write_all(key)?;
+                    value.serialize_to(writer)?;
+                }
+                writer.write_all(b"e")?;
+            }
+            Bencode::Bytes(bytes) => {
+                write!(writer, "{}:", bytes.len())?;
+                writer.write_all(bytes)?;
+            }
+        }
+        return Ok(());
+    }
+
+    /// Feeds the serialized form into a hashing writer, such as a SHA-1
+    /// `Write` adapter, so that e.g. the info-hash of a torrent can be
+    /// computed from `get(b"info")` without an intermediate buffer.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if writing fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::Bencode;
+    ///
+    /// let torrent = Bencode::parse(b"d4:infod6:lengthi3eee".to_vec()).unwrap();
+    /// let mut hasher = vec![];
+    /// torrent.get(b"info").unwrap().hash_into(&mut hasher).unwrap();
+    ///
+    /// assert_eq!(hasher, b"d6:lengthi3ee".to_vec());
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn hash_into<H: std::io::Write>(&self, writer: &mut H) -> std::io::Result<()> {
+        return self.serialize_to(writer);
+    }
+
+    /// Returns an iterator yielding the serialized form in chunks,
+    /// one per scalar value, dict key and structural marker.
+    /// Concatenating the chunks gives the same bytes as `serialize`,
+    /// letting callers write to a socket with their own flow control.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::Bencode;
+    ///
+    /// let list = Bencode::parse(b"li1e4:spame".to_vec()).unwrap();
+    /// let chunks: Vec<Vec<u8>> = list.serialize_chunks().collect();
+    ///
+    /// assert_eq!(
+    ///     chunks,
+    ///     vec![b"l".to_vec(), b"i1e".to_vec(), b"4:spam".to_vec(), b"e".to_vec()]
+    /// );
+    /// ```
+    pub fn serialize_chunks(&self) -> SerializeIter<'_> {
+        return SerializeIter {
+            stack: vec![Chunk::Value(self)],
+        };
+    }
+
+    /// Serializes Bencode types to a lowercase hex string,
+    /// useful for logging over text-only channels.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::Bencode;
+    ///
+    /// assert_eq!(Bencode::Integer(13).to_hex(), "69313365");
+    /// ```
+    pub fn to_hex(&self) -> String {
+        const DIGITS: &[u8; 16] = b"0123456789abcdef";
+        let bytes = self.serialize();
+        let mut hex = String::with_capacity(bytes.len() * 2);
+        for byte in bytes {
+            hex.push(DIGITS[(byte >> 4) as usize] as char);
+            hex.push(DIGITS[(byte & 0xf) as usize] as char);
+        }
+        return hex;
+    }
+
+    /// Parses a hex string, as produced by `to_hex`, into Bencode type.
+    /// Both lowercase and uppercase digits are accepted.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the string isn't valid hex
+    /// or the decoded data doesn't follow the bencode format specification.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::Bencode;
+    ///
+    /// assert_eq!(Bencode::from_hex("69313365"), Ok(Bencode::Integer(13)));
+    /// ```
+    pub fn from_hex(s: &str) -> Result<Self, BencodeError> {
+        let digits = s.as_bytes();
+        if !digits.len().is_multiple_of(2) {
+            return Err(BencodeError::Unexpected(format!(
+                "Odd number of hex digits in string of length {}",
+                digits.len()
+            )));
+        }
+        let mut bytes = Vec::with_capacity(digits.len() / 2);
+        for (i, pair) in digits.chunks_exact(2).enumerate() {
+            let mut byte = 0;
+            for (j, &c) in pair.iter().enumerate() {
+                let value = match (c as char).to_digit(16) {
+                    Some(value) => value as u8,
+                    None => {
+                        return Err(BencodeError::Unexpected(format!(
+                            "Invalid hex digit at index {}",
+                            i * 2 + j
+                        )))
+                    }
+                };
+                byte = byte << 4 | value;
+            }
+            bytes.push(byte);
+        }
+        return Self::parse(bytes);
+    }
+
+    /// Serializes Bencode types to a bytes vector, refusing values
+    /// that `Bencode::parse` could not read back because lists and dicts
+    /// are nested deeper than `DEFAULT_MAX_DEPTH`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the value is nested
+    /// too deeply.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::Bencode;
+    ///
+    /// let list = Bencode::List(vec![Bencode::Integer(1)]);
+    ///
+    /// assert_eq!(list.serialize_canonical(), Ok(b"li1ee".to_vec()));
+    /// ```
+    pub fn serialize_canonical(&self) -> Result<Vec<u8>, BencodeError> {
+        return self.serialize_canonical_with_depth(DEFAULT_MAX_DEPTH);
+    }
+
+    /// Serializes Bencode types to a bytes vector, refusing values nested
+    /// deeper than `max_depth`. Passing the `max_depth` of a `ParseOptions`
+    /// guarantees `Bencode::parse_with_options` can read the output back.
+    /// Like there, the limit is clamped to `Bencode::max_supported_depth`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the value is nested
+    /// too deeply.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::Bencode;
+    ///
+    /// let list = Bencode::List(vec![Bencode::List(vec![])]);
+    ///
+    /// assert!(list.serialize_canonical_with_depth(1).is_err());
+    /// assert_eq!(list.serialize_canonical_with_depth(2), Ok(b"llee".to_vec()));
+    /// ```
+    pub fn serialize_canonical_with_depth(
+        &self,
+        max_depth: usize,
+    ) -> Result<Vec<u8>, BencodeError> {
+        self.check_depth(0, max_depth.min(MAX_SUPPORTED_DEPTH))?;
+        return Ok(self.serialize());
+    }
+
+    fn check_depth(&self, depth: usize, max_depth: usize) -> Result<(), BencodeError> {
+        let children: Vec<&Bencode> = match self {
+            Bencode::Integer(_) | Bencode::Bytes(_) => return Ok(()),
+            Bencode::List(list) => list.iter().collect(),
+            Bencode::Dict(dict) => dict.values().collect(),
+        };
+        if depth + 1 > max_depth {
+            return Err(BencodeError::LimitExceeded(format!(
+                "Nesting depth limit of {} exceeded",
+                max_depth
+            )));
+        }
+        for child in children {
+            child.check_depth(depth + 1, max_depth)?;
+        }
+        return Ok(());
+    }
+
+    fn serialize_into(&self, out: &mut Vec<u8>) {
+        match self {
+            Bencode::Integer(num) => {
+                out.extend(format!("i{}e", num).as_bytes());
+            }
+            Bencode::List(list) => {
+                out.push(b'l');
+                for item in list {
+                    item.serialize_into(out);
+                }
+                out.push(b'e');
+            }
+            Bencode::Dict(dict) => Self::serialize_entries(dict.sorted_iter(), out),
+            Bencode::Bytes(bytes) => {
+                Self::serialize_bytes(bytes, out);
+            }
+        }
+    }
+
+    /// Returns the exact number of bytes `serialize` would produce,
+    /// without allocating.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::Bencode;
+    ///
+    /// let list = Bencode::List(vec![Bencode::Integer(-13)]);
+    ///
+    /// assert_eq!(list.serialized_len(), 7);
+    /// ```
+    pub fn serialized_len(&self) -> usize {
+        match self {
+            Bencode::Integer(num) => {
+                let sign = if *num < 0 { 1 } else { 0 };
+                return 2 + sign + digit_count(num.unsigned_abs());
+            }
+            Bencode::List(list) => {
+                return 2 + list.iter().map(|item| item.serialized_len()).sum::<usize>();
+            }
+            Bencode::Dict(dict) => {
+                return 2 + dict
+                    .iter()
+                    .map(|(key, value)| bytes_serialized_len(key) + value.serialized_len())
+                    .sum::<usize>();
+            }
+            Bencode::Bytes(bytes) => {
+                return bytes_serialized_len(bytes);
+            }
+        }
+    }
+
+    /// Checks whether the input is already in canonical form,
+    /// meaning serializing the parsed value reproduces it byte for byte.
+    /// Inputs with unsorted dict keys are valid but not canonical.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the input data
+    /// doesn't follow the bencode format specification.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::Bencode;
+    ///
+    /// assert_eq!(Bencode::is_canonical(b"d3:cati1e4:spami2ee"), Ok(true));
+    /// assert_eq!(Bencode::is_canonical(b"d4:spami2e3:cati1ee"), Ok(false));
+    /// ```
+    pub fn is_canonical(source: &[u8]) -> Result<bool, BencodeError> {
+        let value = Self::parse_slice(source)?;
+        return Ok(value.serialize() == source);
+    }
+
+    /// Checks whether the input holds exactly one well-formed value,
+    /// with no trailing data, discarding the parsed result.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::Bencode;
+    ///
+    /// assert!(Bencode::is_valid(b"d3:cati1ee"));
+    /// assert!(!Bencode::is_valid(b"d3:cati1e"));
+    /// ```
+    pub fn is_valid(source: &[u8]) -> bool {
+        return Self::parse_slice(source).is_ok();
+    }
+
+    /// Compares two values by their serialized bytes.
+    ///
+    /// Unlike `==`, which compares the parsed structure, this checks
+    /// that both values would produce the exact same encoding.
+    /// Since serialization always sorts dict keys the two agree for
+    /// values built by this crate, but comparing bytes is what matters
+    /// when the output is hashed, e.g. for an info-hash.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::Bencode;
+    ///
+    /// let a = Bencode::parse(b"d4:spami1e3:cati2ee".to_vec()).unwrap();
+    /// let b = Bencode::parse(b"d3:cati2e4:spami1ee".to_vec()).unwrap();
+    ///
+    /// assert!(a.serializes_equal(&b));
+    /// ```
+    pub fn serializes_equal(&self, other: &Bencode) -> bool {
+        return self.serialize() == other.serialize();
+    }
+
+    /// Creates an Integer value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::Bencode;
+    ///
+    /// assert_eq!(Bencode::integer(36), Bencode::Integer(36));
+    /// ```
+    pub fn integer(i: i64) -> Bencode {
+        return Bencode::Integer(i);
+    }
+
+    /// Creates a Bytes value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::Bencode;
+    ///
+    /// assert_eq!(Bencode::bytes("spam"), Bencode::Bytes(b"spam".to_vec()));
+    /// ```
+    pub fn bytes(b: impl Into<Vec<u8>>) -> Bencode {
+        return Bencode::Bytes(b.into());
+    }
+
+    /// Creates a List value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::Bencode;
+    ///
+    /// let list = Bencode::list(vec![Bencode::integer(1)]);
+    ///
+    /// assert_eq!(list, Bencode::List(vec![Bencode::Integer(1)]));
+    /// ```
+    pub fn list(items: Vec<Bencode>) -> Bencode {
+        return Bencode::List(items);
+    }
+
+    /// Creates a Dict value from key and value pairs.
+    /// Keys can be anything convertible to bytes, such as `&str`.
+    /// Later entries replace earlier ones with the same key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::Bencode;
+    ///
+    /// let dict = Bencode::dict([("spam", Bencode::integer(1))]);
+    ///
+    /// assert_eq!(dict.serialize(), b"d4:spami1ee".to_vec());
+    /// ```
+    pub fn dict<K: Into<Vec<u8>>>(entries: impl IntoIterator<Item = (K, Bencode)>) -> Bencode {
+        return Bencode::Dict(entries.into_iter().map(|(k, v)| (k.into(), v)).collect());
+    }
+
+    /// Compares two dict keys in the order bencode sorts them:
+    /// lexicographically by raw bytes, so a key sorts before any
+    /// longer key it is a prefix of. This is the order of a
+    /// `BTreeMap<Vec<u8>, _>` and the order dicts are serialized in.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::cmp::Ordering;
+    /// use bee_code::Bencode;
+    ///
+    /// assert_eq!(Bencode::key_cmp(b"cat", b"spam"), Ordering::Less);
+    /// assert_eq!(Bencode::key_cmp(b"Z", b"a"), Ordering::Less);
+    /// ```
+    pub fn key_cmp(a: &[u8], b: &[u8]) -> Ordering {
+        return a.cmp(b);
+    }
+
+    /// Walks the tree depth first, calling the visitor for every node.
+    /// Dict entries are visited sorted by key, the order they are serialized in.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::{Bencode, Visitor};
+    ///
+    /// struct Sum(i64);
+    ///
+    /// impl Visitor for Sum {
+    ///     fn visit_integer(&mut self, i: i64) {
+    ///         self.0 += i;
+    ///     }
+    /// }
+    ///
+    /// let value = Bencode::parse(b"d1:ai1e1:bli2ei3eee".to_vec()).unwrap();
+    /// let mut sum = Sum(0);
+    /// value.visit(&mut sum);
+    ///
+    /// assert_eq!(sum.0, 6);
+    /// ```
+    pub fn visit<V: Visitor>(&self, visitor: &mut V) {
+        match self {
+            Bencode::Integer(num) => visitor.visit_integer(*num),
+            Bencode::Bytes(bytes) => visitor.visit_bytes(bytes),
+            Bencode::List(list) => {
+                visitor.enter_list();
+                for item in list {
+                    item.visit(visitor);
+                }
+                visitor.leave_list();
+            }
+            Bencode::Dict(dict) => {
+                visitor.enter_dict();
+                for (key, value) in dict.sorted_iter() {
+                    visitor.visit_key(key);
+                    value.visit(visitor);
+                }
+                visitor.leave_dict();
+            }
+        }
+    }
+
+    /// Counts the values of each variant in the tree,
+    /// including this one and every nested value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::Bencode;
+    ///
+    /// let value = Bencode::parse(b"li1e4:spamlee".to_vec()).unwrap();
+    /// let counts = value.variant_counts();
+    ///
+    /// assert_eq!((counts.lists, counts.integers, counts.bytes), (2, 1, 1));
+    /// ```
+    pub fn variant_counts(&self) -> VariantCounts {
+        let mut counts = VariantCounts::default();
+        self.visit(&mut counts);
+        return counts;
+    }
+
+    /// Returns a reference to the contents of a Bytes.
+    /// Returns `None` for any other variant.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Bencode::Bytes(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Takes ownership of the contents of a Bytes.
+    /// Returns `None` for any other variant.
+    pub fn into_bytes(self) -> Option<Vec<u8>> {
+        match self {
+            Bencode::Bytes(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns the contents of a Bytes decoded as UTF-8.
+    /// Returns `None` for any other variant.
+    pub fn as_utf8(&self) -> Option<Result<&str, core::str::Utf8Error>> {
+        return self.as_bytes().map(core::str::from_utf8);
+    }
+
+    /// Returns the contents of a Bytes decoded as UTF-8, replacing
+    /// invalid sequences with `U+FFFD REPLACEMENT CHARACTER`.
+    /// Returns `None` for any other variant.
+    pub fn as_utf8_lossy(&self) -> Option<Cow<'_, str>> {
+        return self.as_bytes().map(String::from_utf8_lossy);
+    }
+
+    /// Returns the value of an Integer.
+    /// Returns `None` for any other variant.
+    pub fn as_integer(&self) -> Option<i64> {
+        match self {
+            Bencode::Integer(num) => Some(*num),
+            _ => None,
+        }
+    }
+
+    /// Takes ownership of the contents of an Integer.
+    /// Returns `None` for any other variant.
+    pub fn into_integer(self) -> Option<i64> {
+        match self {
+            Bencode::Integer(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns a reference to the contents of a List.
+    /// Returns `None` for any other variant.
+    pub fn as_list(&self) -> Option<&[Bencode]> {
+        match self {
+            Bencode::List(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Takes ownership of the contents of a List.
+    /// Returns `None` for any other variant.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::Bencode;
+    ///
+    /// let list = Bencode::parse(b"li1ei2ee".to_vec()).unwrap();
+    ///
+    /// for item in list.into_list().unwrap() {
+    ///     assert!(item.as_integer().is_some());
+    /// }
+    /// ```
+    pub fn into_list(self) -> Option<Vec<Bencode>> {
+        match self {
+            Bencode::List(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns a reference to the contents of a Dict.
+    /// Returns `None` for any other variant.
+    pub fn as_dict(&self) -> Option<&DictMap> {
+        match self {
+            Bencode::Dict(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Takes ownership of the contents of a Dict.
+    /// Returns `None` for any other variant.
+    pub fn into_dict(self) -> Option<DictMap> {
+        match self {
+            Bencode::Dict(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Overlays the entries of another Dict onto this one.
+    /// Nested Dicts present on both sides are merged recursively,
+    /// any other value from `other` replaces the existing one.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if either value is not a Dict.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::Bencode;
+    ///
+    /// let mut base = Bencode::parse(b"d1:ai1e1:bi2ee".to_vec()).unwrap();
+    /// let other = Bencode::parse(b"d1:bi3e1:ci4ee".to_vec()).unwrap();
+    /// base.merge(other).unwrap();
+    ///
+    /// assert_eq!(base.serialize(), b"d1:ai1e1:bi3e1:ci4ee".to_vec());
+    /// ```
+    pub fn merge(&mut self, other: Bencode) -> Result<(), BencodeError> {
+        let (dict, other) = match (self, other) {
+            (Bencode::Dict(dict), Bencode::Dict(other)) => (dict, other),
+            _ => {
+                return Err(BencodeError::TypeMismatch(String::from(
+                    "Only a Dict can be merged into a Dict",
+                )))
+            }
+        };
+        for (key, value) in other {
+            match (dict.get_mut(&key), value) {
+                (Some(existing @ Bencode::Dict(_)), value @ Bencode::Dict(_)) => {
+                    existing.merge(value)?;
+                }
+                (_, value) => {
+                    dict.insert(key, value);
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    /// Decodes a compact peer list, as returned in the `peers` field
+    /// of a tracker response, from a Bytes value. Each peer is 6 bytes:
+    /// an IPv4 address followed by a port, both big endian.
+    /// Returns `None` for any other variant or if the length
+    /// is not a multiple of 6.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::net::{Ipv4Addr, SocketAddrV4};
+    /// use bee_code::Bencode;
+    ///
+    /// let peers = Bencode::Bytes(vec![127, 0, 0, 1, 0x1a, 0xe1]);
+    ///
+    /// assert_eq!(
+    ///     peers.compact_peers(),
+    ///     Some(vec![SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), 6881)])
+    /// );
+    /// ```
+    pub fn compact_peers(&self) -> Option<Vec<SocketAddrV4>> {
+        let bytes = self.as_bytes()?;
+        if bytes.len() % 6 != 0 {
+            return None;
+        }
+        let peers = bytes
+            .chunks_exact(6)
+            .map(|c| {
+                let ip = Ipv4Addr::new(c[0], c[1], c[2], c[3]);
+                SocketAddrV4::new(ip, u16::from_be_bytes([c[4], c[5]]))
+            })
+            .collect();
+        return Some(peers);
+    }
+
+    /// Decodes a compact IPv6 peer list, as returned in the `peers6` field
+    /// of a tracker response, from a Bytes value. Each peer is 18 bytes:
+    /// an IPv6 address followed by a port, both big endian.
+    /// Returns `None` for any other variant or if the length
+    /// is not a multiple of 18.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::net::{Ipv6Addr, SocketAddrV6};
+    /// use bee_code::Bencode;
+    ///
+    /// let mut bytes = Ipv6Addr::LOCALHOST.octets().to_vec();
+    /// bytes.extend(6881u16.to_be_bytes());
+    /// let peers = Bencode::Bytes(bytes);
+    ///
+    /// assert_eq!(
+    ///     peers.compact_peers_v6(),
+    ///     Some(vec![SocketAddrV6::new(Ipv6Addr::LOCALHOST, 6881, 0, 0)])
+    /// );
+    /// ```
+    pub fn compact_peers_v6(&self) -> Option<Vec<SocketAddrV6>> {
+        let bytes = self.as_bytes()?;
+        if bytes.len() % 18 != 0 {
+            return None;
+        }
+        let peers = bytes
+            .chunks_exact(18)
+            .map(|c| {
+                let mut octets = [0; 16];
+                octets.copy_from_slice(&c[..16]);
+                let port = u16::from_be_bytes([c[16], c[17]]);
+                SocketAddrV6::new(Ipv6Addr::from(octets), port, 0, 0)
+            })
+            .collect();
+        return Some(peers);
+    }
+
+    /// Reads the `announce-list` field of a top-level Dict: a list of
+    /// tracker tiers, each a list of UTF-8 tracker URLs.
+    /// Returns `None` if the field is missing or has any other shape.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::Bencode;
+    ///
+    /// let torrent =
+    ///     Bencode::parse(b"d13:announce-listll5:udp:1el5:udp:2eee".to_vec()).unwrap();
+    ///
+    /// assert_eq!(
+    ///     torrent.announce_list(),
+    ///     Some(vec![vec![String::from("udp:1")], vec![String::from("udp:2")]])
+    /// );
+    /// ```
+    pub fn announce_list(&self) -> Option<Vec<Vec<String>>> {
+        return self
+            .get(b"announce-list")?
+            .as_list()?
+            .iter()
+            .map(|tier| {
+                return tier
+                    .as_list()?
+                    .iter()
+                    .map(|url| {
+                        let url = core::str::from_utf8(url.as_bytes()?).ok()?;
+                        return Some(String::from(url));
+                    })
+                    .collect();
+            })
+            .collect();
+    }
+
+    /// Returns the total payload size of a torrent from its `info` dict:
+    /// the `length` of a single-file torrent, or the sum of the `length`
+    /// of every entry in `files` for a multi-file one.
+    /// Returns `None` if neither shape is found or the sum overflows.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::Bencode;
+    ///
+    /// let torrent = Bencode::parse(
+    ///     b"d4:infod5:filesld6:lengthi3eed6:lengthi4eeeee".to_vec(),
+    /// )
+    /// .unwrap();
+    ///
+    /// assert_eq!(torrent.total_length(), Some(7));
+    /// ```
+    pub fn total_length(&self) -> Option<i64> {
+        let info = self.get(b"info")?;
+        if let Some(length) = info.get(b"length") {
+            return length.as_integer();
+        }
+        return info
+            .get(b"files")?
+            .as_list()?
+            .iter()
+            .try_fold(0i64, |total, file| {
+                return total.checked_add(file.get(b"length")?.as_integer()?);
+            });
+    }
+
+    /// Returns the canonical serialized bytes of a torrent's `info` dict,
+    /// the exact input hashed to produce its info-hash.
+    /// Returns `None` if `info` is missing or is not a Dict.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::Bencode;
+    ///
+    /// let torrent = Bencode::parse(b"d4:infod4:name4:spam6:lengthi3eee".to_vec()).unwrap();
+    ///
+    /// assert_eq!(torrent.info_bytes(), Some(b"d6:lengthi3e4:name4:spame".to_vec()));
+    /// ```
+    pub fn info_bytes(&self) -> Option<Vec<u8>> {
+        match self.get(b"info")? {
+            info @ Bencode::Dict(_) => Some(info.serialize()),
+            _ => None,
+        }
+    }
+
+    /// Checks the `pieces` field of a torrent's `info` dict and returns
+    /// the number of 20 byte SHA-1 piece hashes it holds. When `piece length`
+    /// and the total length are present, the piece count must also cover
+    /// the total length exactly.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `info` is missing or not a Dict,
+    /// `pieces` is missing or not a byte string whose length is a multiple of 20,
+    /// a file length is negative, or the piece count disagrees with
+    /// `piece length` and the total length.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::Bencode;
+    ///
+    /// let mut source = b"d4:infod6:lengthi10e12:piece lengthi8e6:pieces40:".to_vec();
+    /// source.extend([0; 40]);
+    /// source.extend(b"ee");
+    /// let torrent = Bencode::parse(source).unwrap();
+    ///
+    /// assert_eq!(torrent.validate_pieces(), Ok(2));
+    /// ```
+    pub fn validate_pieces(&self) -> Result<usize, BencodeError> {
+        let info = match self.get(b"info") {
+            Some(info @ Bencode::Dict(_)) => info,
+            Some(_) => {
+                return Err(BencodeError::Unexpected(String::from(
+                    "Info field is not a Dict",
+                )))
+            }
+            None => return Err(BencodeError::Unexpected(String::from("Missing info dict"))),
+        };
+        let pieces = match info.get(b"pieces") {
+            Some(Bencode::Bytes(pieces)) => pieces,
+            Some(_) => {
+                return Err(BencodeError::Unexpected(String::from(
+                    "Pieces field is not a byte string",
+                )))
+            }
+            None => {
+                return Err(BencodeError::Unexpected(String::from(
+                    "Missing pieces field",
+                )))
+            }
+        };
+        if pieces.len() % 20 != 0 {
+            return Err(BencodeError::Unexpected(format!(
+                "Pieces length {} is not a multiple of 20",
+                pieces.len()
+            )));
+        }
+        let count = pieces.len() / 20;
+        let files = info.get(b"files").and_then(|files| files.as_list());
+        for (index, file) in files.into_iter().flatten().enumerate() {
+            match file.get(b"length").and_then(|l| l.as_integer()) {
+                Some(length) if length < 0 => {
+                    return Err(BencodeError::Unexpected(format!(
+                        "Negative length {} for file {}",
+                        length, index
+                    )))
+                }
+                _ => {}
+            }
+        }
+        let total = self.total_length();
+        if let Some(total) = total.filter(|&total| total < 0) {
+            return Err(BencodeError::Unexpected(format!(
+                "Negative total length {}",
+                total
+            )));
+        }
+        let piece_length = info.get(b"piece length").and_then(|l| l.as_integer());
+        if let (Some(piece_length), Some(total)) = (piece_length, total) {
+            if piece_length <= 0 {
+                return Err(BencodeError::Unexpected(format!(
+                    "Invalid piece length {}",
+                    piece_length
+                )));
+            }
+            let expected = (total as u64).div_ceil(piece_length as u64);
+            if expected != count as u64 {
+                return Err(BencodeError::Unexpected(format!(
+                    "Expected {} pieces for a total length of {} but found {}",
+                    expected, total, count
+                )));
+            }
+        }
+        return Ok(count);
+    }
+
+    /// Returns the number of entries in a List or Dict,
+    /// or the number of bytes in a Bytes value.
+    /// Returns `None` for an Integer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::Bencode;
+    ///
+    /// let list = Bencode::parse(b"li1ei2ee".to_vec()).unwrap();
+    ///
+    /// assert_eq!(list.len(), Some(2));
+    /// assert_eq!(Bencode::Integer(1).len(), None);
+    /// ```
+    pub fn len(&self) -> Option<usize> {
+        match self {
+            Bencode::Bytes(bytes) => Some(bytes.len()),
+            Bencode::Integer(_) => None,
+            Bencode::List(list) => Some(list.len()),
+            Bencode::Dict(dict) => Some(dict.len()),
+        }
+    }
+
+    /// Returns whether a List, Dict or Bytes value has no entries.
+    /// Returns `None` for an Integer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::Bencode;
+    ///
+    /// let list = Bencode::parse(b"le".to_vec()).unwrap();
+    ///
+    /// assert_eq!(list.is_empty(), Some(true));
+    /// assert_eq!(Bencode::Integer(0).is_empty(), None);
+    /// ```
+    pub fn is_empty(&self) -> Option<bool> {
+        return self.len().map(|len| len == 0);
+    }
+
+    /// Looks up a key in a Dict.
+    /// Returns `None` if the value is not a Dict or the key is missing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::Bencode;
+    ///
+    /// let dict = Bencode::parse(b"d4:spami1ee".to_vec()).unwrap();
+    ///
+    /// assert_eq!(dict.get(b"spam"), Some(&Bencode::Integer(1)));
+    /// ```
+    pub fn get(&self, key: &[u8]) -> Option<&Bencode> {
+        match self {
+            Bencode::Dict(dict) => dict.get(key),
+            _ => None,
+        }
+    }
+
+    /// Looks up a key in a Dict, returning a mutable reference
+    /// to its value so it can be edited in place.
+    /// Returns `None` if the value is not a Dict or the key is missing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::Bencode;
+    ///
+    /// let mut dict = Bencode::parse(b"d4:spami1ee".to_vec()).unwrap();
+    /// if let Some(value) = dict.get_mut(b"spam") {
+    ///     *value = Bencode::Integer(2);
+    /// }
+    ///
+    /// assert_eq!(dict.serialize(), b"d4:spami2ee".to_vec());
+    /// ```
+    pub fn get_mut(&mut self, key: &[u8]) -> Option<&mut Bencode> {
+        match self {
+            Bencode::Dict(dict) => dict.get_mut(key),
+            _ => None,
+        }
+    }
+
+    /// Inserts an entry into a Dict, returning the value previously
+    /// stored under the key. Does nothing and returns `None` if the
+    /// value is not a Dict, dropping `value`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::Bencode;
+    ///
+    /// let mut dict = Bencode::parse(b"d4:spami1ee".to_vec()).unwrap();
+    ///
+    /// assert_eq!(dict.insert(b"cat".to_vec(), Bencode::Integer(2)), None);
+    /// assert_eq!(dict.serialize(), b"d3:cati2e4:spami1ee".to_vec());
+    /// ```
+    pub fn insert(&mut self, key: Vec<u8>, value: Bencode) -> Option<Bencode> {
+        match self {
+            Bencode::Dict(dict) => dict.insert(key, value),
+            _ => None,
+        }
+    }
+
+    /// Looks up a string key in a Dict.
+    /// Returns `None` if the value is not a Dict or the key is missing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::Bencode;
+    ///
+    /// let dict = Bencode::parse(b"d4:spami1ee".to_vec()).unwrap();
+    ///
+    /// assert_eq!(dict.get_str("spam"), Some(&Bencode::Integer(1)));
+    /// ```
+    pub fn get_str(&self, key: &str) -> Option<&Bencode> {
+        return self.get(key.as_bytes());
+    }
+
+    /// Returns an iterator over the keys of a Dict decoded as UTF-8.
+    /// Keys that are not valid UTF-8 are yielded as errors.
+    /// Returns `None` if the value is not a Dict.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::Bencode;
+    ///
+    /// let dict = Bencode::parse(b"d3:cati1e4:spami2ee".to_vec()).unwrap();
+    /// let keys: Vec<_> = dict.str_keys().unwrap().collect();
+    ///
+    /// assert_eq!(keys, vec![Ok("cat"), Ok("spam")]);
+    /// ```
+    pub fn str_keys(&self) -> Option<impl Iterator<Item = Result<&str, core::str::Utf8Error>>> {
+        match self {
+            Bencode::Dict(dict) => Some(dict.keys().map(|k| core::str::from_utf8(k))),
+            _ => None,
+        }
+    }
+
+    /// Returns an iterator over the entries of a Dict in the map's
+    /// own order. With the `preserve_order` feature this is the order
+    /// the keys appeared in the input, otherwise they are sorted by key.
+    /// Returns `None` if the value is not a Dict.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::Bencode;
+    ///
+    /// let dict = Bencode::parse(b"d3:cati1e4:spami2ee".to_vec()).unwrap();
+    /// let keys: Vec<_> = dict.entries_in_order().unwrap().map(|(k, _)| k).collect();
+    ///
+    /// assert_eq!(keys, vec![b"cat".as_slice(), b"spam".as_slice()]);
+    /// ```
+    pub fn entries_in_order(&self) -> Option<impl Iterator<Item = (&[u8], &Bencode)>> {
+        match self {
+            Bencode::Dict(dict) => Some(dict.iter().map(|(k, v)| (k.as_slice(), v))),
+            _ => None,
+        }
+    }
+
+    /// Returns the entries of a Dict sorted by key,
+    /// or `None` if the value is not a Dict.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::Bencode;
+    ///
+    /// let dict = Bencode::parse(b"d4:spami2e3:cati1ee".to_vec()).unwrap();
+    ///
+    /// assert_eq!(
+    ///     dict.entries(),
+    ///     Some(vec![
+    ///         (b"cat".as_slice(), &Bencode::Integer(1)),
+    ///         (b"spam".as_slice(), &Bencode::Integer(2)),
+    ///     ])
+    /// );
+    /// ```
+    pub fn entries(&self) -> Option<Vec<(&[u8], &Bencode)>> {
+        return Some(self.as_dict()?.sorted_entries());
+    }
+
+    /// Consumes a Dict, returning its entries sorted by key,
+    /// or `None` if the value is not a Dict.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bee_code::Bencode;
+    ///
+    /// let dict = Bencode::parse(b"d4:spami2e3:cati1ee".to_vec()).unwrap();
+    ///
+    /// assert_eq!(
+    ///     dict.into_entries(),
+    ///     Some(vec![
+    ///         (b"cat".to_vec(), Bencode::Integer(1)),
+    ///         (b"spam".to_vec(), Bencode::Integer(2)),
+    ///     ])
+    /// );
+    /// ```
+    pub fn into_entries(self) -> Option<Vec<(Vec<u8>, Bencode)>> {
+        let mut entries: Vec<_> = self.into_dict()?.into_iter().collect();
+        entries.sort_by(|a, b| Self::key_cmp(&a.0, &b.0));
+        return Some(entries);
+    }
+
+    fn serialize_entries<'a, I>(entries: I, out: &mut Vec<u8>)
+    where
+        I: IntoIterator<Item = (&'a [u8], &'a Bencode)>,
+    {
+        out.push(b'd');
+        for (key, value) in entries {
+            Self::serialize_bytes(key, out);
+            value.serialize_into(out);
+        }
+        out.push(b'e');
+    }
+
+    fn serialize_bytes(bytes: &[u8], out: &mut Vec<u8>) {
+        out.extend(format!("{}:", bytes.len()).as_bytes());
+        out.extend(bytes);
+    }
+}
+
+/// Iterator over the serialized chunks of a value,
+/// returned by `Bencode::serialize_chunks`.
+pub struct SerializeIter<'a> {
+    stack: Vec<Chunk<'a>>,
+}
+
+enum Chunk<'a> {
+    Value(&'a Bencode),
+    Key(&'a [u8]),
+    End,
+}
+
+impl Iterator for SerializeIter<'_> {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut out = vec![];
+        match self.stack.pop()? {
+            Chunk::Value(Bencode::List(list)) => {
+                self.stack.push(Chunk::End);
+                self.stack.extend(list.iter().rev().map(Chunk::Value));
+                out.push(b'l');
+            }
+            Chunk::Value(Bencode::Dict(dict)) => {
+                self.stack.push(Chunk::End);
+                for (key, value) in dict.sorted_iter().rev() {
+                    self.stack.push(Chunk::Value(value));
+                    self.stack.push(Chunk::Key(key));
+                }
+                out.push(b'd');
+            }
+            Chunk::Value(value) => value.serialize_into(&mut out),
+            Chunk::Key(key) => Bencode::serialize_bytes(key, &mut out),
+            Chunk::End => out.push(b'e'),
+        }
+        return Some(out);
+    }
+}
+
+/// Decodes consecutive Bencode values from a single buffer.
+///
+/// # Examples
+///
+/// ```
+/// use bee_code::{Bencode, Decoder};
+///
+/// let mut decoder = Decoder::new(b"i1e4:spam");
+///
+/// assert_eq!(decoder.next_value(), Some(Ok(Bencode::Integer(1))));
+/// assert_eq!(decoder.position(), 3);
+/// assert_eq!(decoder.next_value(), Some(Ok(Bencode::Bytes(b"spam".to_vec()))));
+/// assert_eq!(decoder.next_value(), None);
+/// ```
+pub struct Decoder<'a> {
+    parser: Parser<'a>,
+    failed: bool,
+}
+
+impl Decoder<'_> {
+    /// Creates a decoder reading from the start of the input.
+    pub fn new(input: &[u8]) -> Decoder<'_> {
+        return Decoder {
+            parser: Parser::new(input),
+            failed: false,
+        };
+    }
+
+    /// Decodes the next value in the input.
+    /// Returns `None` once the input is exhausted or after an error.
+    pub fn next_value(&mut self) -> Option<Result<Bencode, BencodeError>> {
+        if self.failed || self.parser.eof() {
+            return None;
+        }
+        let res = self.parser.decode();
+        self.failed = res.is_err();
+        return Some(res);
+    }
+
+    /// Returns the index of the first byte not yet decoded.
+    pub fn position(&self) -> usize {
+        return self.parser.pos;
+    }
+}
+
+/// Decodes values from data that arrives in chunks, such as reads
+/// from a socket. Bytes are buffered until a complete value is
+/// available, and anything after it is kept for the next value.
+///
+/// Each call to `try_decode` parses the buffered value again from its
+/// first byte, so a value split into many small chunks costs time
+/// quadratic in its length. Use `with_options` to bound `max_bytes`
+/// when the input is untrusted; `new` buffers without limit.
+///
+/// An error leaves the buffer untouched, since the end of the invalid
+/// value is unknown, so `try_decode` keeps returning it. Call `clear`
+/// to start over, or `into_buffer` to inspect the bytes that failed.
+///
+/// # Examples
+///
+/// ```
+/// use bee_code::{Bencode, PartialDecoder};
+///
+/// let mut decoder = PartialDecoder::new();
+/// decoder.push(b"i3");
+/// assert_eq!(decoder.try_decode(), Ok(None));
+/// decoder.push(b"6e");
+/// assert_eq!(decoder.try_decode(), Ok(Some(Bencode::Integer(36))));
+/// ```
+#[derive(Debug, Default)]
+pub struct PartialDecoder {
+    buffer: Vec<u8>,
+    options: ParseOptions,
+}
+
+impl PartialDecoder {
+    /// Creates a decoder with an empty buffer and the default options.
+    pub fn new() -> PartialDecoder {
+        return PartialDecoder::with_options(ParseOptions::default());
+    }
+
+    /// Creates a decoder with an empty buffer that applies `options`
+    /// to every value, like `Bencode::parse_with_options`. An incomplete
+    /// value is also rejected once more than `max_bytes` are buffered.
+    pub fn with_options(options: ParseOptions) -> PartialDecoder {
+        return PartialDecoder {
+            buffer: vec![],
+            options,
+        };
+    }
+
+    /// Appends bytes to the buffer.
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// Discards the buffered bytes, such as after an error.
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+    }
+
+    /// Returns the bytes that have not been decoded yet.
+    pub fn into_buffer(self) -> Vec<u8> {
+        return self.buffer;
+    }
+
+    /// Decodes the next value from the buffered bytes.
+    /// Returns `Ok(None)` if more bytes are needed to complete it.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the buffered data
+    /// doesn't follow the bencode format specification or
+    /// breaks one of the configured checks or limits.
+    pub fn try_decode(&mut self) -> Result<Option<Bencode>, BencodeError> {
+        if self.buffer.is_empty() {
+            return Ok(None);
+        }
+        let mut parser = Parser::with_options(&self.buffer, self.options);
+        parser.partial = true;
+        match parser.decode() {
+            Ok(value) => {
+                let len = parser.pos;
+                self.buffer.drain(..len);
+                return Ok(Some(value));
+            }
+            Err(BencodeError::UnexpectedEof(_)) if self.buffer.len() > self.options.max_bytes => {
+                return Err(BencodeError::LimitExceeded(format!(
+                    "Buffered {} bytes of an incomplete value, exceeding the limit of {} bytes",
+                    self.buffer.len(),
+                    self.options.max_bytes
+                )))
+            }
+            Err(BencodeError::UnexpectedEof(_)) => return Ok(None),
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Decodes many independent inputs with one decoder, such as
+/// tracker responses handled in a loop. Each input is copied into
+/// a buffer owned by the decoder, so the caller's read buffer can be
+/// dropped or refilled right away. The buffer keeps its capacity between
+/// inputs, so once it has grown to fit the largest one no further
+/// allocation is needed for the input.
+///
+/// # Examples
+///
+/// ```
+/// use bee_code::{Bencode, ReusableDecoder};
+///
+/// let mut decoder = ReusableDecoder::new();
+/// decoder.reset(b"i1e");
+/// assert_eq!(decoder.decode(), Ok(Bencode::Integer(1)));
+/// decoder.reset(b"4:spam");
+/// assert_eq!(decoder.decode(), Ok(Bencode::Bytes(b"spam".to_vec())));
+/// ```
+#[derive(Debug, Default)]
+pub struct ReusableDecoder {
+    buffer: Vec<u8>,
+    options: ParseOptions,
+}
+
+impl ReusableDecoder {
+    /// Creates a decoder with an empty buffer and the default options.
+    pub fn new() -> ReusableDecoder {
+        return ReusableDecoder::with_options(ParseOptions::default());
+    }
+
+    /// Creates a decoder with an empty buffer that applies `options`
+    /// to every input, like `Bencode::parse_with_options`.
+    pub fn with_options(options: ParseOptions) -> ReusableDecoder {
+        return ReusableDecoder {
+            buffer: vec![],
+            options,
+        };
+    }
+
+    /// Replaces the buffered input with a copy of `input`,
+    /// reusing the buffer's allocation.
+    pub fn reset(&mut self, input: &[u8]) {
+        self.buffer.clear();
+        self.buffer.extend_from_slice(input);
+    }
+
+    /// Decodes the buffered input as a single value.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the buffered data
+    /// doesn't follow the bencode format specification or
+    /// breaks one of the configured checks or limits.
+    pub fn decode(&self) -> Result<Bencode, BencodeError> {
+        return Parser::with_options(&self.buffer, self.options).decode_complete();
+    }
+}
+
+struct Parser<'a> {
+    pos: usize,
+    input: &'a [u8],
+    max_bytes: usize,
+    allocated: usize,
+    max_elements: usize,
+    elements: usize,
+    max_depth: usize,
+    depth: usize,
+    deepest: usize,
+    spans: Option<KeySpans>,
+    spanned: Option<Vec<(Option<Vec<u8>>, SpannedBencode)>>,
+    errors: Option<Vec<BencodeError>>,
+    partial: bool,
+    last_string: Option<(usize, Range<usize>)>,
+    check_canonical: bool,
+    check_duplicates: bool,
+}
+
+impl<'a> Parser<'a> {
+    fn new(bytes: &[u8]) -> Parser<'_> {
+        return Parser {
+            input: bytes,
+            pos: 0,
+            max_bytes: usize::MAX,
+            allocated: 0,
+            max_elements: usize::MAX,
+            elements: 0,
+            max_depth: DEFAULT_MAX_DEPTH,
+            depth: 0,
+            deepest: 0,
+            spans: None,
+            spanned: None,
+            errors: None,
+            partial: false,
+            last_string: None,
+            check_canonical: false,
+            check_duplicates: false,
+        };
+    }
+
+    fn with_options(bytes: &[u8], options: ParseOptions) -> Parser<'_> {
+        let mut parser = Parser::new(bytes);
+        parser.check_canonical = options.check_canonical;
+        parser.check_duplicates = options.check_duplicates;
+        parser.max_depth = options.max_depth.min(MAX_SUPPORTED_DEPTH);
+        parser.max_bytes = options.max_bytes;
+        parser.max_elements = options.max_elements;
+        return parser;
+    }
+
+    fn decode(&mut self) -> Result<Bencode, BencodeError> {
+        self.expect_value()?;
+        return self.parse_element();
+    }
+
+    fn expect_value(&self) -> Result<(), BencodeError> {
+        if self.eof() {
+            return Err(BencodeError::UnexpectedEof(format!(
+                "Expected a value at index {} but the input is empty",
+                self.pos
+            )));
+        }
+        return Ok(());
+    }
+
+    fn decode_complete(&mut self) -> Result<Bencode, BencodeError> {
+        let value = self.decode()?;
+        if !self.eof() {
+            return Err(self.trailing_error());
+        }
+        return Ok(value);
+    }
+
+    fn trailing_error(&self) -> BencodeError {
+        return BencodeError::TrailingData(format!(
+            "Trailing data after value at index {}",
+            self.pos
+        ));
+    }
+
+    // Records a failed container element when collecting errors and skips
+    // ahead to the next byte that could start an element or end the container.
+    // Running out of input or hitting a limit can't be recovered from,
+    // nor can anything once the input is exhausted.
+    fn recover(&mut self, start: usize, error: BencodeError) -> Result<(), BencodeError> {
+        let errors = match &mut self.errors {
+            Some(errors) => errors,
+            None => return Err(error),
+        };
+        if self.pos >= self.input.len()
+            || matches!(
+                error,
+                BencodeError::UnexpectedEof(_) | BencodeError::LimitExceeded(_)
+            )
+        {
+            return Err(error);
+        }
+        errors.push(error);
+        self.pos = self.pos.max(start + 1);
+        self.consume_while(|c| !matches!(c, b'd' | b'l' | b'i' | b'e' | b'0'..=b'9'));
+        return Ok(());
+    }
+
+    fn allocate(&mut self, size: usize) -> Result<(), BencodeError> {
+        self.allocated = self.allocated.saturating_add(size);
+        if self.allocated > self.max_bytes {
+            return Err(BencodeError::LimitExceeded(format!(
+                "Allocation limit of {} bytes exceeded at index {}",
+                self.max_bytes, self.pos
+            )));
+        }
+        return Ok(());
+    }
+
+    fn enter(&mut self) -> Result<(), BencodeError> {
+        self.depth += 1;
+        self.deepest = self.deepest.max(self.depth);
+        if self.depth > self.max_depth {
+            return Err(BencodeError::LimitExceeded(format!(
+                "Nesting depth limit of {} exceeded at index {}",
+                self.max_depth,
+                self.pos - 1
+            )));
+        }
+        return Ok(());
+    }
+
+    fn next(&self) -> Result<u8, BencodeError> {
+        match self.input.get(self.pos) {
+            Some(c) => Ok(*c),
+            None => Err(self.eof_error()),
+        }
+    }
+
+    fn eof(&self) -> bool {
+        return self.pos >= self.input.len();
+    }
+
+    fn eof_error(&self) -> BencodeError {
+        return BencodeError::UnexpectedEof(format!(
+            "Unexpected end of input at index {}",
+            self.pos
+        ));
+    }
+
+    fn consume(&mut self) -> Result<u8, BencodeError> {
+        let c = self.next()?;
+        self.pos += 1;
+        return Ok(c);
+    }
+
+    fn consume_while<F>(&mut self, test: F) -> &'a [u8]
+    where
+        F: Fn(u8) -> bool,
+    {
+        let start = self.pos;
+        while let Some(&c) = self.input.get(self.pos) {
+            if !test(c) {
+                break;
+            }
+            self.pos += 1;
+        }
+        return &self.input[start..self.pos];
+    }
+
+    /// Consumes bytes up to, but not including, `terminator`.
+    /// Running out of input first is an `UnexpectedEof` error.
+    fn consume_until(&mut self, terminator: u8) -> Result<&'a [u8], BencodeError> {
+        let res = self.consume_while(|c| c != terminator);
+        if self.eof() {
+            return Err(self.eof_error());
+        }
+        return Ok(res);
+    }
+
+    fn consume_expected(&mut self, expected: u8) -> Result<u8, BencodeError> {
+        match self.next()? {
+            c if c == expected => self.consume(),
+            c => Err(BencodeError::Unexpected(format!(
+                "Unexpected character at index {}. Expected {} found {}",
+                self.pos, expected, c
+            ))),
+        }
+    }
+
+    fn parse_dict(&mut self) -> Result<Bencode, BencodeError> {
+        return Ok(Bencode::Dict(self.parse_map()?));
+    }
+
+    fn parse_map<M: BencodeMap>(&mut self) -> Result<M, BencodeError> {
+        self.consume_expected(b'd')?;
+        self.enter()?;
+        let mut dict = M::default();
+        let mut last_key = None;
+
+        while self.next_in_container()? != b'e' {
+            let start = self.pos;
+            match self.parse_entry(&dict, last_key.as_deref()) {
+                Ok((k, v)) => {
+                    if self.check_canonical {
+                        last_key = Some(k.clone());
+                    }
+                    dict.insert_entry(k, v);
+                }
+                Err(e) => self.recover(start, e)?,
+            }
+        }
+        self.consume_expected(b'e')?;
+        self.depth -= 1;
+        return Ok(dict);
+    }
+
+    fn parse_entry<M: BencodeMap>(
+        &mut self,
+        dict: &M,
+        last_key: Option<&[u8]>,
+    ) -> Result<(Vec<u8>, Bencode), BencodeError> {
+        if !self.next()?.is_ascii_digit() {
+            return Err(BencodeError::Unexpected(format!(
+                "Dict key must be a byte string at index {}",
+                self.pos
+            )));
+        }
+        let key_start = self.pos;
+        let k = self.parse_string()?;
+        let order = last_key.map(|last| Bencode::key_cmp(last, &k));
+        let duplicate = if self.check_canonical {
+            order == Some(Ordering::Equal)
+        } else {
+            let spanned_key = self.depth == 1 && self.spans.is_some();
+            (self.check_duplicates || spanned_key) && dict.contains_entry(&k)
+        };
+        if duplicate {
+            return Err(BencodeError::Unexpected(format!(
+                "Duplicate dict key at index {}",
+                key_start
+            )));
+        }
+        if self.check_canonical && order == Some(Ordering::Greater) {
+            return Err(BencodeError::Unexpected(format!(
+                "Dict key out of order at index {}",
+                key_start
+            )));
+        }
+        let start = self.pos;
+        let v = self.parse_element().map_err(|e| e.in_value_of(&k))?;
+        if let Some((key, _)) = self.spanned.as_mut().and_then(|done| done.last_mut()) {
+            *key = Some(k.clone());
+        }
+        if self.depth == 1 {
+            if let Some(spans) = &mut self.spans {
+                spans.push((k.clone(), start..self.pos));
+            }
+        }
+        return Ok((k, v));
+    }
+
+    fn parse_list(&mut self) -> Result<Bencode, BencodeError> {
+        self.consume_expected(b'l')?;
+        self.enter()?;
+        let mut list = vec![];
+        while self.next_in_container()? != b'e' {
+            let start = self.pos;
+            match self.parse_element() {
+                Ok(value) => list.push(value),
+                Err(e) => self.recover(start, e)?,
+            }
+        }
+        self.consume_expected(b'e')?;
+        self.depth -= 1;
+        return Ok(Bencode::List(list));
+    }
+
+    fn parse_element(&mut self) -> Result<Bencode, BencodeError> {
+        self.allocate(core::mem::size_of::<Bencode>())?;
+        let start = self.pos;
+        let children = self.spanned.as_ref().map(|done| done.len());
+        let mut value = match self.next()? {
+            b'd' => self.parse_dict()?,
+            b'l' => self.parse_list()?,
+            b'i' => Bencode::Integer(self.parse_int()?),
+            b'0'..=b'9' => Bencode::Bytes(self.parse_string()?),
+            _ => {
+                return Err(BencodeError::Unexpected(format!(
+                    "Unexpected value type at index {}",
+                    self.pos
+                )))
+            }
+        };
+        self.elements += 1;
+        if self.elements > self.max_elements {
+            return Err(BencodeError::LimitExceeded(format!(
+                "Element limit of {} exceeded at index {}",
+                self.max_elements, self.pos
+            )));
+        }
+        // The spanned form of the top-level value is built by the caller.
+        if let (Some(done), Some(children), 1..) = (&mut self.spanned, children, self.depth) {
+            let children = done.split_off(children);
+            done.push((None, Self::spanned(&mut value, children, start..self.pos)));
+        }
+        return Ok(value);
+    }
+
+    // Turns a parsed value into its spanned form, given the spanned
+    // forms of its children in input order. Only the spanned tree is
+    // returned to the caller, so byte strings are moved rather than cloned.
+    fn spanned(
+        value: &mut Bencode,
+        children: Vec<(Option<Vec<u8>>, SpannedBencode)>,
+        span: Range<usize>,
+    ) -> SpannedBencode {
+        let value = match value {
+            Bencode::Integer(int) => SpannedValue::Integer(*int),
+            Bencode::Bytes(bytes) => SpannedValue::Bytes(core::mem::take(bytes)),
+            Bencode::List(_) => {
+                SpannedValue::List(children.into_iter().map(|(_, child)| child).collect())
+            }
+            Bencode::Dict(_) => SpannedValue::Dict(
+                children
+                    .into_iter()
+                    .map(|(key, child)| (key.unwrap_or_default(), child))
+                    .collect(),
+            ),
+        };
+        return SpannedBencode { value, span };
+    }
+
+    fn parse_int(&mut self) -> Result<i64, BencodeError> {
+        let pos = self.pos;
+        self.consume_expected(b'i')?;
+        let mut sign = 1;
+        if self.next()? == b'-' {
+            self.pos += 1;
+            sign = -1;
+        }
+        let digits = self.pos;
+        let v = self.consume_until(b'e')?;
+        self.consume_expected(b'e')?;
+        if v.is_empty() {
+            let message = if sign == -1 {
+                "Sign without digits while parsing integer"
+            } else {
+                "Empty integer"
+            };
+            return Err(BencodeError::Unexpected(format!(
+                "{} at index {}",
+                message, pos
+            )));
+        }
+        if v.first() == Some(&b'+') {
+            return Err(BencodeError::Unexpected(format!(
+                "Positive sign while parsing integer at index {}",
+                digits
+            )));
+        }
+        if sign == -1 && v.iter().all(|&c| c == b'0') {
+            return Err(BencodeError::Unexpected(format!(
+                "Negative 0 while parsing integer at index {}",
+                pos
+            )));
+        }
+        if v.len() > 1 && v[0] == b'0' {
+            return Err(BencodeError::Unexpected(format!(
+                "Leading 0 while parsing integer at index {}",
+                pos
+            )));
+        }
+        let magnitude = match parse_digits(v) {
+            Some(magnitude) => magnitude,
+            None => match core::str::from_utf8(v) {
+                Ok(_) => {
+                    return Err(BencodeError::Unexpected(format!(
+                        "Invalid integer value at index {}",
+                        pos
+                    )))
+                }
+                Err(e) => {
+                    return Err(BencodeError::Utf8Error(format!(
+                        "Non UTF8 encoded integer value at index {}. {}",
+                        pos, e
+                    )))
+                }
+            },
+        };
+        let int = if sign == -1 {
+            0i64.checked_sub_unsigned(magnitude)
+        } else {
+            i64::try_from(magnitude).ok()
+        };
+        let int = match int {
+            Some(int) => int,
+            None => {
+                return Err(BencodeError::Unexpected(format!(
+                    "Integer out of range at index {}",
+                    pos
+                )))
+            }
+        };
+        return Ok(int);
+    }
+
+    fn parse_string(&mut self) -> Result<Vec<u8>, BencodeError> {
+        let start = self.pos;
+        let len = self.parse_len()?;
+        self.consume_expected(b':')?;
+        self.allocate(len)?;
+        let end = self.pos.saturating_add(len);
+        let bytes = match self.input.get(self.pos..end) {
+            Some(bytes) => bytes.to_vec(),
+            None => {
+                let rest = self.input.get(self.pos..).unwrap_or_default();
+                if self.swallowed_terminators(rest) {
+                    return Err(Self::past_container_error(len, start));
+                }
+                self.pos = self.input.len();
+                return Err(self.eof_error());
+            }
+        };
+        self.last_string = Some((start, self.pos..end));
+        self.pos = end;
+        return Ok(bytes);
+    }
+
+    // Like `next`, but at the end of input inside a container, blames a
+    // byte string that ended exactly there after swallowing the closing
+    // 'e' of every open container.
+    fn next_in_container(&self) -> Result<u8, BencodeError> {
+        if self.eof() {
+            if let Some((start, payload)) = &self.last_string {
+                if payload.end == self.input.len()
+                    && self.swallowed_terminators(&self.input[payload.clone()])
+                {
+                    return Err(Self::past_container_error(payload.len(), *start));
+                }
+            }
+        }
+        return self.next();
+    }
+
+    // Checks whether a byte string payload reaching the end of the input
+    // ends with enough 'e's to close every open container. A partial
+    // input may still grow, so it never counts as swallowed.
+    fn swallowed_terminators(&self, payload: &[u8]) -> bool {
+        let closing = payload.iter().rev().take_while(|&&c| c == b'e').count();
+        return self.depth > 0 && !self.partial && closing >= self.depth;
+    }
+
+    fn past_container_error(len: usize, start: usize) -> BencodeError {
+        return BencodeError::Unexpected(format!(
+            "Byte string of length {} at index {} runs past the end of its container",
+            len, start
+        ));
+    }
+
+    fn parse_len(&mut self) -> Result<usize, BencodeError> {
+        let pos = self.pos;
+        match self.next()? {
+            b'0'..=b'9' => {}
+            b'-' => {
+                return Err(BencodeError::NegativeLen(format!(
+                    "Negative string len at index {}",
+                    pos,
+                )))
+            }
+            _ => {
+                return Err(BencodeError::Unexpected(format!(
+                    "String length must start with a digit at index {}",
+                    pos
+                )))
+            }
+        }
+        let v = self.consume_until(b':')?;
+        if v.len() > 1 && v[0] == b'0' {
+            return Err(BencodeError::Unexpected(format!(
+                "Leading 0 while parsing string length at index {}",
+                pos
+            )));
+        }
+        let len = match parse_digits(v).and_then(|len| usize::try_from(len).ok()) {
+            Some(len) => len,
+            None => match core::str::from_utf8(v) {
+                Ok(_) => {
+                    return Err(BencodeError::Unexpected(format!(
+                        "Invalid string length at index {}",
+                        pos
+                    )))
+                }
+                Err(e) => {
+                    return Err(BencodeError::Utf8Error(format!(
+                        "Non UTF8 encoded string length at index {}. {}",
+                        pos, e
+                    )))
+                }
+            },
+        };
+        return Ok(len);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        collections::{BTreeMap, HashSet},
+        net::{Ipv4Addr, Ipv6Addr, SocketAddrV4, SocketAddrV6},
+    };
+
+    use crate::{
+        key, parse_digits, Bencode, BencodeMap, Decoder, DictMap, ParseOptions, ParseStats, Parser,
+        PartialDecoder, ReusableDecoder, SpannedBencode, SpannedValue, VariantCounts, Visitor,
+        DEFAULT_MAX_DEPTH,
+    };
+
+    #[test]
+    fn test_parse_string() {
+        let mut p = Parser::new(b"6:string");
+        assert_eq!(p.parse_string(), Ok(b"string".to_vec()));
+    }
+    #[test]
+    fn test_parse_string_empty() {
+        let mut p = Parser::new(b"0:");
+        assert_eq!(p.parse_string(), Ok(b"".to_vec()));
+    }
+    #[test]
+    fn test_parse_string_with_neg_len() {
+        let mut p = Parser::new(b"-2:text");
+        assert_eq!(
+            p.parse_string(),
+            Err(crate::BencodeError::NegativeLen(
+                "Negative string len at index 0".to_owned(),
+            ))
+        );
+    }
+    #[test]
+    fn test_parse_string_with_plus_len() {
+        let mut p = Parser::new(b"+3:abc");
+        assert_eq!(
+            p.parse_string(),
+            Err(crate::BencodeError::Unexpected(
+                "String length must start with a digit at index 0".to_owned(),
+            ))
+        );
+    }
+    #[test]
+    fn test_parse_dict_neg_len_key() {
+        let mut p = Parser::new(b"d1:ai1e-3:dogi1ee");
+        assert_eq!(
+            p.parse_dict(),
+            Err(crate::BencodeError::Unexpected(
+                "Dict key must be a byte string at index 7".to_owned(),
+            ))
+        );
+    }
+    #[test]
+    fn test_parse_string_lead_zero_len() {
+        let mut p = Parser::new(b"03:abc");
+        assert_eq!(
+            p.parse_string(),
+            Err(crate::BencodeError::Unexpected(
+                "Leading 0 while parsing string length at index 0".to_owned(),
+            ))
+        );
+        assert_eq!(Bencode::parse(b"0:".to_vec()), Ok(Bencode::Bytes(vec![])));
+    }
+    #[test]
+    fn test_parse_int() {
+        let mut p = Parser::new(b"i13e");
+        assert_eq!(p.parse_int(), Ok(13));
+    }
+    #[test]
+    fn test_parse_int_neg() {
+        let mut p = Parser::new(b"i-13e");
+        assert_eq!(p.parse_int(), Ok(-13));
+    }
+    #[test]
+    fn test_parse_int_neg_zero() {
+        let mut p = Parser::new(b"i-0e");
+        assert_eq!(
+            p.parse_int(),
+            Err(crate::BencodeError::Unexpected(
+                "Negative 0 while parsing integer at index 0".to_owned()
+            ))
+        );
+    }
+    #[test]
+    fn test_parse_int_lead_zero() {
+        let mut p = Parser::new(b"i0934e");
+        assert_eq!(
+            p.parse_int(),
+            Err(crate::BencodeError::Unexpected(
                 "Leading 0 while parsing integer at index 0".to_owned()
             ))
         );
     }
 
     #[test]
-    fn test_parse_list() {
-        let mut p = Parser::new(b"l4:spam3:doge");
+    fn test_parse_int_zero_forms() {
+        let neg_zero = Err(crate::BencodeError::Unexpected(
+            "Negative 0 while parsing integer at index 0".to_owned(),
+        ));
+        let lead_zero = Err(crate::BencodeError::Unexpected(
+            "Leading 0 while parsing integer at index 0".to_owned(),
+        ));
+        assert_eq!(Parser::new(b"i-0e").parse_int(), neg_zero);
+        assert_eq!(Parser::new(b"i-00e").parse_int(), neg_zero);
+        assert_eq!(Parser::new(b"i00e").parse_int(), lead_zero);
+        assert_eq!(Parser::new(b"i012e").parse_int(), lead_zero);
+        assert_eq!(Parser::new(b"i-012e").parse_int(), lead_zero);
+        assert_eq!(Parser::new(b"i0e").parse_int(), Ok(0));
+    }
+    #[test]
+    fn test_parse_int_plus_sign() {
+        let mut p = Parser::new(b"i+3e");
+        assert_eq!(
+            p.parse_int(),
+            Err(crate::BencodeError::Unexpected(
+                "Positive sign while parsing integer at index 1".to_owned()
+            ))
+        );
+        let mut p = Parser::new(b"i3e");
+        assert_eq!(p.parse_int(), Ok(3));
+    }
+
+    #[test]
+    fn test_parse_list() {
+        let mut p = Parser::new(b"l4:spam3:doge");
+        assert_eq!(
+            p.parse_list(),
+            Ok(Bencode::List(vec![
+                Bencode::Bytes(b"spam".to_vec()),
+                Bencode::Bytes(b"dog".to_vec()),
+            ]))
+        );
+    }
+    #[test]
+    fn test_parse_list_empty() {
+        let mut p = Parser::new(b"le");
+        assert_eq!(p.parse_list(), Ok(Bencode::List(vec![])));
+    }
+    #[test]
+    fn test_parse_dict() {
+        let mut p = Parser::new(b"d4:spam3:dog3:cati36ee");
+        assert_eq!(
+            p.parse_dict(),
+            Ok(Bencode::Dict(
+                BTreeMap::from([
+                    (b"spam".to_vec(), Bencode::Bytes(b"dog".to_vec())),
+                    (b"cat".to_vec(), Bencode::Integer(36)),
+                ])
+                .into()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_dict_int_key() {
+        let mut p = Parser::new(b"di3e3:dog");
+        assert_eq!(
+            p.parse_dict(),
+            Err(crate::BencodeError::Unexpected(
+                "Dict key must be a byte string at index 1".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_dict_empty() {
+        let mut p = Parser::new(b"de");
+        assert_eq!(p.parse_dict(), Ok(Bencode::Dict(DictMap::new())));
+    }
+
+    #[test]
+    fn test_parse_prefix() {
+        assert_eq!(
+            Bencode::parse_prefix(b"i1eREST"),
+            Ok((Bencode::Integer(1), 3))
+        );
+    }
+    #[test]
+    fn test_parse_trailing_data() {
+        assert_eq!(
+            Bencode::parse(b"i1eREST".to_vec()),
+            Err(crate::BencodeError::TrailingData(
+                "Trailing data after value at index 3".to_owned()
+            ))
+        );
+    }
+    #[test]
+    fn test_hash_dict_insertion_order() {
+        let mut first = DictMap::new();
+        first.insert(b"spam".to_vec(), Bencode::Integer(1));
+        first.insert(b"cat".to_vec(), Bencode::Integer(2));
+        let mut second = DictMap::new();
+        second.insert(b"cat".to_vec(), Bencode::Integer(2));
+        second.insert(b"spam".to_vec(), Bencode::Integer(1));
+
+        let mut set = HashSet::new();
+        set.insert(Bencode::Dict(first));
+        set.insert(Bencode::Dict(second));
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn test_get_str() {
+        let dict = Bencode::parse(b"d8:announce3:url4:infodee".to_vec()).unwrap();
+        assert_eq!(
+            dict.get_str("announce"),
+            Some(&Bencode::Bytes(b"url".to_vec()))
+        );
+        assert_eq!(dict.get_str("missing"), None);
+        assert_eq!(Bencode::Integer(1).get_str("announce"), None);
+    }
+    #[test]
+    fn test_str_keys_non_utf8() {
+        let dict = Bencode::Dict(DictMap::from([
+            (b"info".to_vec(), Bencode::Integer(1)),
+            (vec![0xff, 0xfe], Bencode::Integer(2)),
+        ]));
+        let keys: Vec<_> = dict.str_keys().unwrap().collect();
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[0], Ok("info"));
+        assert!(keys[1].is_err());
+        assert!(Bencode::List(vec![]).str_keys().is_none());
+    }
+
+    #[test]
+    fn test_is_canonical_sorted() {
+        assert_eq!(Bencode::is_canonical(b"d3:cati36e4:spam3:doge"), Ok(true));
+        assert_eq!(Bencode::is_canonical(b"li1e4:spame"), Ok(true));
+    }
+    #[test]
+    fn test_is_canonical_unsorted() {
+        assert_eq!(Bencode::is_canonical(b"d4:spam3:dog3:cati36ee"), Ok(false));
+    }
+
+    #[cfg(feature = "preserve_order")]
+    #[test]
+    fn test_parse_dict_preserves_order() {
+        let dict = Bencode::parse(b"d4:spami1e3:cati2ee".to_vec()).unwrap();
+        let keys: Vec<_> = dict.entries_in_order().unwrap().map(|(k, _)| k).collect();
+        assert_eq!(keys, vec![b"spam".as_slice(), b"cat".as_slice()]);
+        assert_eq!(dict.serialize(), b"d3:cati2e4:spami1ee".to_vec());
+    }
+
+    #[test]
+    fn test_parse_with_limit() {
+        let mut source = b"d4:data1000:".to_vec();
+        source.extend([b'a'; 1000]);
+        source.push(b'e');
+        assert_eq!(
+            Bencode::parse_with_limit(source.clone(), 500),
+            Err(crate::BencodeError::LimitExceeded(
+                "Allocation limit of 500 bytes exceeded at index 12".to_owned()
+            ))
+        );
+        assert!(Bencode::parse_with_limit(source, 10_000).is_ok());
+    }
+
+    #[test]
+    fn test_serializes_equal() {
+        let a = Bencode::parse(b"d4:spami1e3:cati2ee".to_vec()).unwrap();
+        let b = Bencode::parse(b"d3:cati2e4:spami1ee".to_vec()).unwrap();
+        assert_eq!(a, b);
+        assert!(a.serializes_equal(&b));
+    }
+    #[test]
+    fn test_serializes_equal_differs() {
+        let int = Bencode::Integer(36);
+        let bytes = Bencode::Bytes(b"36".to_vec());
+        assert_ne!(int, bytes);
+        assert!(!int.serializes_equal(&bytes));
+    }
+
+    #[test]
+    fn test_decoder_multiple_values() {
+        let mut decoder = Decoder::new(b"i36el4:spame");
+        assert_eq!(decoder.position(), 0);
+        assert_eq!(decoder.next_value(), Some(Ok(Bencode::Integer(36))));
+        assert_eq!(decoder.position(), 4);
+        assert_eq!(
+            decoder.next_value(),
+            Some(Ok(Bencode::List(vec![Bencode::Bytes(b"spam".to_vec())])))
+        );
+        assert_eq!(decoder.position(), 12);
+        assert_eq!(decoder.next_value(), None);
+    }
+
+    #[cfg(feature = "preserve_order")]
+    #[test]
+    fn test_serialize_unsorted_map() {
+        let mut dict = DictMap::new();
+        dict.insert(b"zebra".to_vec(), Bencode::Integer(1));
+        dict.insert(b"apple".to_vec(), Bencode::Integer(2));
+        dict.insert(b"mango".to_vec(), Bencode::Integer(3));
+        assert_eq!(
+            Bencode::Dict(dict).serialize(),
+            b"d5:applei2e5:mangoi3e5:zebrai1ee".to_vec()
+        );
+    }
+
+    #[test]
+    fn test_parse_with_element_limit() {
+        let mut source = b"l".to_vec();
+        for _ in 0..1000 {
+            source.extend(b"i0e");
+        }
+        source.push(b'e');
+        assert_eq!(
+            Bencode::parse_with_element_limit(source.clone(), 500),
+            Err(crate::BencodeError::LimitExceeded(
+                "Element limit of 500 exceeded at index 1504".to_owned()
+            ))
+        );
+        assert!(Bencode::parse_with_element_limit(source, 2000).is_ok());
+    }
+
+    #[test]
+    fn test_partial_decoder_split_value() {
+        let mut decoder = PartialDecoder::new();
+        decoder.push(b"i3");
+        assert_eq!(decoder.try_decode(), Ok(None));
+        decoder.push(b"6e");
+        assert_eq!(decoder.try_decode(), Ok(Some(Bencode::Integer(36))));
+        assert_eq!(decoder.try_decode(), Ok(None));
+    }
+
+    #[test]
+    fn test_partial_decoder_after_error() {
+        let mut decoder = PartialDecoder::new();
+        decoder.push(b"i1ex");
+        assert_eq!(decoder.try_decode(), Ok(Some(Bencode::Integer(1))));
+        assert!(decoder.try_decode().is_err());
+        assert!(decoder.try_decode().is_err());
+        decoder.clear();
+        decoder.push(b"i2e");
+        assert_eq!(decoder.try_decode(), Ok(Some(Bencode::Integer(2))));
+
+        decoder.push(b"xi3e");
+        assert!(decoder.try_decode().is_err());
+        assert_eq!(decoder.into_buffer(), b"xi3e".to_vec());
+    }
+
+    #[test]
+    fn test_partial_decoder_limit() {
+        let limited = ParseOptions {
+            max_bytes: 1000,
+            ..ParseOptions::default()
+        };
+        let mut decoder = PartialDecoder::with_options(limited);
+        decoder.push(b"999999999999:");
+        assert_eq!(
+            decoder.try_decode(),
+            Err(crate::BencodeError::LimitExceeded(
+                "Allocation limit of 1000 bytes exceeded at index 13".to_owned()
+            ))
+        );
+
+        let mut decoder = PartialDecoder::with_options(limited);
+        decoder.push(&[b'1'; 1000]);
+        assert_eq!(decoder.try_decode(), Ok(None));
+        decoder.push(b"111");
+        assert_eq!(
+            decoder.try_decode(),
+            Err(crate::BencodeError::LimitExceeded(
+                "Buffered 1003 bytes of an incomplete value, exceeding the limit of 1000 bytes"
+                    .to_owned()
+            ))
+        );
+    }
+    #[test]
+    fn test_partial_decoder_keeps_leftover() {
+        let mut decoder = PartialDecoder::new();
+        decoder.push(b"4:spaml3:d");
+        assert_eq!(
+            decoder.try_decode(),
+            Ok(Some(Bencode::Bytes(b"spam".to_vec())))
+        );
+        assert_eq!(decoder.try_decode(), Ok(None));
+        decoder.push(b"oge");
+        assert_eq!(
+            decoder.try_decode(),
+            Ok(Some(Bencode::List(vec![Bencode::Bytes(b"dog".to_vec())])))
+        );
+    }
+
+    #[test]
+    fn test_len() {
+        assert_eq!(Bencode::Bytes(b"spam".to_vec()).len(), Some(4));
+        assert_eq!(Bencode::Integer(36).len(), None);
+        assert_eq!(
+            Bencode::List(vec![Bencode::Integer(1), Bencode::Integer(2)]).len(),
+            Some(2)
+        );
+        assert_eq!(Bencode::List(vec![]).len(), Some(0));
+        assert_eq!(
+            Bencode::Dict(DictMap::from([(b"cat".to_vec(), Bencode::Integer(1))])).len(),
+            Some(1)
+        );
+        assert_eq!(Bencode::Dict(DictMap::new()).len(), Some(0));
+    }
+    #[test]
+    fn test_is_empty() {
+        assert_eq!(Bencode::Bytes(vec![]).is_empty(), Some(true));
+        assert_eq!(Bencode::Bytes(b"spam".to_vec()).is_empty(), Some(false));
+        assert_eq!(Bencode::Integer(0).is_empty(), None);
+        assert_eq!(Bencode::List(vec![]).is_empty(), Some(true));
+        assert_eq!(
+            Bencode::List(vec![Bencode::Integer(1)]).is_empty(),
+            Some(false)
+        );
+        assert_eq!(Bencode::Dict(DictMap::new()).is_empty(), Some(true));
+        assert_eq!(
+            Bencode::Dict(DictMap::from([(b"cat".to_vec(), Bencode::Integer(1))])).is_empty(),
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn test_parse_with_spans() {
+        let source = b"d8:announce3:url4:infod4:name4:spam6:lengthi36eee";
+        let (value, spans) = Bencode::parse_with_spans(source).unwrap();
+        assert_eq!(value, Bencode::parse(source.to_vec()).unwrap());
+        assert_eq!(spans.len(), 2);
+        let (key, range) = &spans[1];
+        assert_eq!(key, &b"info".to_vec());
+        assert_eq!(&source[range.clone()], b"d4:name4:spam6:lengthi36ee");
+    }
+    #[test]
+    fn test_parse_with_spans_not_dict() {
+        let (_, spans) = Bencode::parse_with_spans(b"ld1:ai1eee").unwrap();
+        assert!(spans.is_empty());
+    }
+
+    #[test]
+    fn test_parse_with_spans_duplicate_key() {
+        assert_eq!(
+            Bencode::parse_with_spans(b"d1:ai1e1:ai2ee"),
+            Err(crate::BencodeError::Unexpected(
+                "Duplicate dict key at index 7".to_owned()
+            ))
+        );
+        let (_, spans) = Bencode::parse_with_spans(b"d1:ad1:ai1e1:ai2eee").unwrap();
+        assert_eq!(spans, vec![(b"a".to_vec(), 4..18)]);
+    }
+
+    #[test]
+    fn test_into_list() {
+        let list = Bencode::List(vec![Bencode::Integer(1), Bencode::Bytes(b"spam".to_vec())]);
+        let items = list.into_list().unwrap();
+        assert_eq!(
+            items,
+            vec![Bencode::Integer(1), Bencode::Bytes(b"spam".to_vec())]
+        );
+    }
+    #[test]
+    fn test_into_mismatched_variant() {
+        assert_eq!(Bencode::Integer(1).into_list(), None);
+        assert_eq!(Bencode::Integer(1).into_bytes(), None);
+        assert_eq!(Bencode::Integer(1).into_dict(), None);
+        assert_eq!(Bencode::Bytes(b"1".to_vec()).into_integer(), None);
+        assert_eq!(Bencode::Integer(1).into_integer(), Some(1));
+        assert_eq!(
+            Bencode::Bytes(b"spam".to_vec()).into_bytes(),
+            Some(b"spam".to_vec())
+        );
+        assert_eq!(
+            Bencode::Dict(DictMap::new()).into_dict(),
+            Some(DictMap::new())
+        );
+    }
+    #[test]
+    fn test_as_accessors() {
+        let value = Bencode::parse(b"d4:listli1ee4:name4:spame".to_vec()).unwrap();
+        assert_eq!(value.as_dict().map(|d| d.len()), Some(2));
+        assert_eq!(
+            value.get(b"name").and_then(|v| v.as_bytes()),
+            Some(b"spam".as_slice())
+        );
+        assert_eq!(
+            value.get(b"list").and_then(|v| v.as_list()),
+            Some([Bencode::Integer(1)].as_slice())
+        );
+        assert_eq!(value.as_integer(), None);
+        assert_eq!(value.as_list(), None);
+    }
+
+    #[test]
+    fn test_serialized_len() {
+        let value = Bencode::parse(
+            b"d4:infod5:filesld6:lengthi-1234567890e4:pathl1:a2:bceee4:name0:e4:listli0eleee"
+                .to_vec(),
+        )
+        .unwrap();
+        assert_eq!(value.serialized_len(), value.serialize().len());
+        assert_eq!(
+            Bencode::Integer(i64::MIN).serialized_len(),
+            Bencode::Integer(i64::MIN).serialize().len()
+        );
+        let mut long = vec![];
+        long.resize(12345, b'a');
+        let bytes = Bencode::Bytes(long);
+        assert_eq!(bytes.serialized_len(), bytes.serialize().len());
+    }
+
+    #[derive(Default)]
+    struct CountingVisitor {
+        integers: usize,
+        bytes: usize,
+        lists: usize,
+        dicts: usize,
+        keys: usize,
+        open: isize,
+    }
+
+    impl Visitor for CountingVisitor {
+        fn visit_integer(&mut self, _i: i64) {
+            self.integers += 1;
+        }
+        fn visit_bytes(&mut self, _b: &[u8]) {
+            self.bytes += 1;
+        }
+        fn enter_list(&mut self) {
+            self.lists += 1;
+            self.open += 1;
+        }
+        fn leave_list(&mut self) {
+            self.open -= 1;
+        }
+        fn enter_dict(&mut self) {
+            self.dicts += 1;
+            self.open += 1;
+        }
+        fn visit_key(&mut self, _key: &[u8]) {
+            self.keys += 1;
+        }
+        fn leave_dict(&mut self) {
+            self.open -= 1;
+        }
+    }
+
+    #[test]
+    fn test_visit_counts() {
+        let value =
+            Bencode::parse(b"d4:infod6:lengthi3e4:name4:spame4:listli1eli2e3:dogedeee".to_vec())
+                .unwrap();
+        let mut visitor = CountingVisitor::default();
+        value.visit(&mut visitor);
+        assert_eq!(visitor.integers, 3);
+        assert_eq!(visitor.bytes, 2);
+        assert_eq!(visitor.lists, 2);
+        assert_eq!(visitor.dicts, 3);
+        assert_eq!(visitor.keys, 4);
+        assert_eq!(visitor.open, 0);
+    }
+
+    #[test]
+    fn test_merge_overwrite() {
+        let mut base = Bencode::parse(b"d8:announce3:old4:name4:spame".to_vec()).unwrap();
+        let other = Bencode::parse(b"d8:announce3:newe".to_vec()).unwrap();
+        assert_eq!(base.merge(other), Ok(()));
+        assert_eq!(
+            base,
+            Bencode::parse(b"d8:announce3:new4:name4:spame".to_vec()).unwrap()
+        );
+    }
+    #[test]
+    fn test_merge_nested() {
+        let mut base = Bencode::parse(b"d4:infod6:lengthi1e4:name4:spamee".to_vec()).unwrap();
+        let other = Bencode::parse(b"d4:infod6:lengthi2e7:privatei1eee".to_vec()).unwrap();
+        assert_eq!(base.merge(other), Ok(()));
+        assert_eq!(
+            base,
+            Bencode::parse(b"d4:infod6:lengthi2e4:name4:spam7:privatei1eee".to_vec()).unwrap()
+        );
+    }
+    #[test]
+    fn test_merge_type_mismatch() {
+        let mut base = Bencode::parse(b"d1:ai1ee".to_vec()).unwrap();
+        assert_eq!(
+            base.merge(Bencode::List(vec![])),
+            Err(crate::BencodeError::TypeMismatch(
+                "Only a Dict can be merged into a Dict".to_owned()
+            ))
+        );
+        let mut list = Bencode::List(vec![]);
+        assert!(list.merge(Bencode::Dict(DictMap::new())).is_err());
+    }
+
+    #[test]
+    fn test_parse_empty_input() {
+        assert_eq!(
+            Bencode::parse(Vec::new()),
+            Err(crate::BencodeError::UnexpectedEof(
+                "Expected a value at index 0 but the input is empty".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_constructors() {
+        assert_eq!(Bencode::integer(-3), Bencode::Integer(-3));
+        assert_eq!(Bencode::integer(-3).serialize(), b"i-3e".to_vec());
+        assert_eq!(Bencode::bytes("dog"), Bencode::Bytes(b"dog".to_vec()));
+        assert_eq!(
+            Bencode::bytes(b"dog".to_vec()).serialize(),
+            b"3:dog".to_vec()
+        );
+        let list = Bencode::list(vec![Bencode::integer(1), Bencode::bytes("a")]);
+        assert_eq!(
+            list,
+            Bencode::List(vec![Bencode::Integer(1), Bencode::Bytes(b"a".to_vec())])
+        );
+        assert_eq!(list.serialize(), b"li1e1:ae".to_vec());
+        let dict = Bencode::dict([
+            (b"spam".to_vec(), Bencode::integer(1)),
+            (b"cat".to_vec(), Bencode::bytes("dog")),
+        ]);
+        assert_eq!(
+            dict,
+            Bencode::Dict(DictMap::from([
+                (b"cat".to_vec(), Bencode::Bytes(b"dog".to_vec())),
+                (b"spam".to_vec(), Bencode::Integer(1)),
+            ]))
+        );
+        assert_eq!(dict.serialize(), b"d3:cat3:dog4:spami1ee".to_vec());
+    }
+
+    #[test]
+    fn test_compact_peers() {
+        let peers = Bencode::Bytes(vec![10, 0, 0, 1, 0x1a, 0xe1, 192, 168, 1, 20, 0x00, 0x50]);
+        assert_eq!(
+            peers.compact_peers(),
+            Some(vec![
+                SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, 1), 6881),
+                SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 20), 80),
+            ])
+        );
+        assert_eq!(Bencode::Bytes(vec![1, 2, 3]).compact_peers(), None);
+        assert_eq!(Bencode::Integer(6).compact_peers(), None);
+    }
+
+    #[test]
+    fn test_compact_peers_v6() {
+        let mut bytes = vec![0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1];
+        bytes.extend([0x1a, 0xe1]);
+        assert_eq!(
+            Bencode::Bytes(bytes).compact_peers_v6(),
+            Some(vec![SocketAddrV6::new(
+                Ipv6Addr::new(0x2001, 0x0db8, 0, 0, 0, 0, 0, 1),
+                6881,
+                0,
+                0
+            )])
+        );
+        assert_eq!(Bencode::Bytes(vec![0; 12]).compact_peers_v6(), None);
+        assert_eq!(Bencode::Integer(18).compact_peers_v6(), None);
+    }
+
+    // Inputs that used to panic while parsing, mostly found by fuzzing.
+    const MALFORMED_INPUTS: &[&[u8]] = &[
+        b"",
+        b"i",
+        b"i-",
+        b"ie",
+        b"i-e",
+        b"i13",
+        b"iabce",
+        b"i--1e",
+        b"i99999999999999999999e",
+        b"i-9223372036854775809e",
+        b"i9223372036854775808e",
+        b"i\xffe",
+        b"l",
+        b"li1e",
+        b"d",
+        b"d3:foo",
+        b"d3:fooi1e",
+        b"di1ei2ee",
+        b"3",
+        b"3:",
+        b"3:ab",
+        b"3abc",
+        b"3a:abc",
+        b"99999999999999999999:a",
+        b"18446744073709551615:a",
+        b"l18446744073709551615:ae",
+        b"d1:a",
+        b"x",
+        b"-",
+        b"l-1:ae",
+        &[b'l'; 4096],
+        &[b'd'; 4096],
+    ];
+
+    #[test]
+    fn test_malformed_inputs_do_not_panic() {
+        for input in MALFORMED_INPUTS {
+            assert!(Bencode::parse(input.to_vec()).is_err(), "{:?}", input);
+            let _ = Bencode::parse_prefix(input);
+            let _ = Bencode::parse_with_spans(input);
+            let mut decoder = Decoder::new(input);
+            while decoder.next_value().is_some() {}
+            let mut decoder = PartialDecoder::new();
+            decoder.push(input);
+            let _ = decoder.try_decode();
+        }
+    }
+    #[test]
+    fn test_parse_int_bounds() {
+        assert_eq!(
+            Bencode::parse(b"i-9223372036854775808e".to_vec()),
+            Ok(Bencode::Integer(i64::MIN))
+        );
+        assert_eq!(
+            Bencode::parse(b"i9223372036854775807e".to_vec()),
+            Ok(Bencode::Integer(i64::MAX))
+        );
+        assert_eq!(
+            Bencode::parse(b"i9223372036854775808e".to_vec()),
+            Err(crate::BencodeError::Unexpected(
+                "Integer out of range at index 0".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_serialize_canonical() {
+        let value = Bencode::parse(b"d4:infod6:lengthi3ee4:listli1eee".to_vec()).unwrap();
+        assert_eq!(value.serialize_canonical(), Ok(value.serialize()));
+    }
+    #[test]
+    fn test_serialize_canonical_too_deep() {
+        let mut value = Bencode::List(vec![]);
+        for _ in 0..256 {
+            value = Bencode::List(vec![value]);
+        }
+        assert_eq!(
+            value.serialize_canonical(),
+            Err(crate::BencodeError::LimitExceeded(
+                "Nesting depth limit of 256 exceeded".to_owned()
+            ))
+        );
+        assert!(Bencode::parse(value.serialize()).is_err());
+
+        let deeper = ParseOptions {
+            max_depth: Bencode::max_supported_depth(),
+            ..ParseOptions::default()
+        };
+        let serialized = value.serialize_canonical_with_depth(deeper.max_depth);
+        assert_eq!(serialized, Ok(value.serialize()));
+        assert_eq!(
+            Bencode::parse_with_options(serialized.unwrap(), deeper),
+            Ok(value)
+        );
+    }
+
+    #[test]
+    fn test_key() {
+        assert_eq!(key("info"), b"info".to_vec());
+        assert!(key("a") < key("ab"));
+        assert!(key("announce") < key("info"));
+        let from_str = Bencode::dict([
+            ("info", Bencode::integer(1)),
+            ("announce", Bencode::integer(2)),
+        ]);
+        let from_string = Bencode::dict([
+            (String::from("info"), Bencode::integer(1)),
+            (String::from("announce"), Bencode::integer(2)),
+        ]);
+        let from_bytes = Bencode::dict([
+            (b"info".to_vec(), Bencode::integer(1)),
+            (b"announce".to_vec(), Bencode::integer(2)),
+        ]);
+        assert_eq!(from_str, from_bytes);
+        assert_eq!(from_string, from_bytes);
+        assert_eq!(from_str.serialize(), b"d8:announcei2e4:infoi1ee".to_vec());
+    }
+
+    #[test]
+    fn test_parse_collecting_list() {
+        let (value, errors) = Bencode::parse_collecting(b"l1:axi-0e2:bce");
+        assert_eq!(
+            errors,
+            vec![
+                crate::BencodeError::Unexpected("Unexpected value type at index 4".to_owned()),
+                crate::BencodeError::Unexpected(
+                    "Negative 0 while parsing integer at index 5".to_owned()
+                ),
+            ]
+        );
+        assert_eq!(
+            value,
+            Some(Bencode::List(vec![
+                Bencode::Bytes(b"a".to_vec()),
+                Bencode::Bytes(b"bc".to_vec()),
+            ]))
+        );
+    }
+    #[test]
+    fn test_parse_collecting_dict() {
+        let (value, errors) = Bencode::parse_collecting(b"d1:ai1e1:bi01e1:ci3ee");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            value,
+            Some(Bencode::dict([
+                ("a", Bencode::integer(1)),
+                ("c", Bencode::integer(3)),
+            ]))
+        );
+    }
+    #[test]
+    fn test_parse_collecting_fatal() {
+        let (value, errors) = Bencode::parse_collecting(b"li1e");
+        assert_eq!(value, None);
+        assert_eq!(errors.len(), 1);
+        let (value, errors) = Bencode::parse_collecting(b"i1e");
+        assert_eq!(value, Some(Bencode::Integer(1)));
+        assert!(errors.is_empty());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_into_io_error() {
+        let e = crate::BencodeError::Unexpected("Unexpected value type at index 3".to_owned());
+        let io: std::io::Error = e.into();
+        assert_eq!(io.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(io.to_string(), "Unexpected value type at index 3");
+
+        fn read() -> std::io::Result<Bencode> {
+            return Ok(Bencode::parse(b"x".to_vec())?);
+        }
+        assert_eq!(read().unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    fn nested_lists(depth: usize) -> Vec<u8> {
+        let mut source = vec![b'l'; depth];
+        source.extend(vec![b'e'; depth]);
+        return source;
+    }
+
+    #[test]
+    fn test_default_max_depth() {
+        assert!(Bencode::parse(nested_lists(DEFAULT_MAX_DEPTH)).is_ok());
+        assert_eq!(
+            Bencode::parse(nested_lists(DEFAULT_MAX_DEPTH + 1)),
+            Err(crate::BencodeError::LimitExceeded(format!(
+                "Nesting depth limit of {} exceeded at index {}",
+                DEFAULT_MAX_DEPTH, DEFAULT_MAX_DEPTH
+            )))
+        );
+    }
+    #[test]
+    fn test_max_supported_depth() {
+        let depth = Bencode::max_supported_depth();
+        let source = nested_lists(depth);
+        let mut p = Parser::new(&source);
+        p.max_depth = depth;
+        assert!(p.decode_complete().is_ok());
+
+        let mut source = b"d1:a".repeat(depth - 1);
+        source.extend(b"de");
+        source.extend(vec![b'e'; depth - 1]);
+        let mut p = Parser::new(&source);
+        p.max_depth = depth;
+        assert!(p.decode_complete().is_ok());
+    }
+
+    #[test]
+    fn test_hex_round_trip() {
+        let dict = Bencode::dict([
+            ("name", Bencode::bytes("spam")),
+            ("length", Bencode::integer(36)),
+        ]);
+        let hex = dict.to_hex();
+        assert_eq!(hex, "64363a6c656e67746869333665343a6e616d65343a7370616d65");
+        assert_eq!(Bencode::from_hex(&hex), Ok(dict));
+        assert!(Bencode::from_hex(&hex.to_uppercase()).is_ok());
+    }
+    #[test]
+    fn test_from_hex_invalid() {
+        assert_eq!(
+            Bencode::from_hex("69zz65"),
+            Err(crate::BencodeError::Unexpected(
+                "Invalid hex digit at index 2".to_owned()
+            ))
+        );
+        assert_eq!(
+            Bencode::from_hex("693"),
+            Err(crate::BencodeError::Unexpected(
+                "Odd number of hex digits in string of length 3".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_into_maps() {
+        let source = b"d4:spami1e3:cati2e5:applei3ee";
+        let canonical = b"d5:applei3e3:cati2e4:spami1ee".to_vec();
+
+        let pairs: Vec<(Vec<u8>, Bencode)> = Bencode::parse_into(source).unwrap();
+        let keys: Vec<_> = pairs.entries_iter().map(|(k, _)| k).collect();
+        assert_eq!(keys, vec![b"spam".as_slice(), b"cat", b"apple"]);
+        assert_eq!(Bencode::serialize_map(&pairs), canonical);
+
+        let tree: BTreeMap<Vec<u8>, Bencode> = Bencode::parse_into(source).unwrap();
+        assert_eq!(Bencode::serialize_map(&tree), canonical);
+    }
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_parse_into_hash_map() {
+        use std::collections::HashMap;
+
+        let source = b"d4:spami1e3:cati2e5:applei3ee";
+        let hash: HashMap<Vec<u8>, Bencode> = Bencode::parse_into(source).unwrap();
+        assert_eq!(hash.get(b"cat".as_slice()), Some(&Bencode::Integer(2)));
+        assert_eq!(
+            Bencode::serialize_map(&hash),
+            b"d5:applei3e3:cati2e4:spami1ee".to_vec()
+        );
+
+        let res: Result<HashMap<Vec<u8>, Bencode>, _> = Bencode::parse_into(b"li1ee");
+        assert!(matches!(res, Err(crate::BencodeError::TypeMismatch(_))));
+    }
+    #[test]
+    fn test_parse_into_not_dict() {
+        let res: Result<BTreeMap<Vec<u8>, Bencode>, _> = Bencode::parse_into(b"li1ee");
+        assert_eq!(
+            res,
+            Err(crate::BencodeError::TypeMismatch(
+                "Expected a Dict at index 0".to_owned()
+            ))
+        );
+    }
+    #[test]
+    fn test_vec_map_keeps_duplicates() {
+        let pairs: Vec<(Vec<u8>, Bencode)> = Bencode::parse_into(b"d1:ai1e1:bi2e1:ai3ee").unwrap();
+        assert_eq!(
+            pairs,
+            vec![
+                (b"a".to_vec(), Bencode::Integer(1)),
+                (b"b".to_vec(), Bencode::Integer(2)),
+                (b"a".to_vec(), Bencode::Integer(3)),
+            ]
+        );
+
+        let serialized = Bencode::serialize_map(&pairs);
+        assert_eq!(serialized, b"d1:ai3e1:bi2ee".to_vec());
+        assert_eq!(Bencode::is_canonical(&serialized), Ok(true));
+    }
+
+    #[test]
+    fn test_parse_string_past_container_end() {
+        assert_eq!(
+            Bencode::parse(b"l5:abce".to_vec()),
+            Err(crate::BencodeError::Unexpected(
+                "Byte string of length 5 at index 1 runs past the end of its container".to_owned()
+            ))
+        );
+        assert!(matches!(
+            Bencode::parse(b"5:abce".to_vec()),
+            Err(crate::BencodeError::UnexpectedEof(_))
+        ));
+
+        let mut decoder = PartialDecoder::new();
+        decoder.push(b"l5:abce");
+        assert_eq!(decoder.try_decode(), Ok(None));
+        decoder.push(b"fe");
+        assert_eq!(
+            decoder.try_decode(),
+            Ok(Some(Bencode::List(vec![Bencode::bytes("abcef")])))
+        );
+    }
+
+    #[test]
+    fn test_truncated_string_with_e_in_payload() {
+        assert_eq!(
+            Bencode::parse(b"l6:hello".to_vec()),
+            Err(crate::BencodeError::UnexpectedEof(
+                "Unexpected end of input at index 8".to_owned()
+            ))
+        );
+        assert_eq!(
+            Bencode::parse(b"l6:world".to_vec()),
+            Bencode::parse(b"l6:hello".to_vec())
+        );
+        assert!(matches!(
+            Bencode::parse(b"ll6:abce".to_vec()),
+            Err(crate::BencodeError::UnexpectedEof(_))
+        ));
+    }
+
+    #[test]
+    fn test_string_swallowing_terminators() {
+        assert_eq!(
+            Bencode::parse(b"ll5:abcee".to_vec()),
+            Err(crate::BencodeError::Unexpected(
+                "Byte string of length 5 at index 2 runs past the end of its container".to_owned()
+            ))
+        );
+        assert_eq!(
+            Bencode::parse(b"d1:a2:ee".to_vec()),
+            Err(crate::BencodeError::Unexpected(
+                "Byte string of length 2 at index 4 runs past the end of its container".to_owned()
+            ))
+        );
+        assert!(matches!(
+            Bencode::parse(b"ll4:abcee".to_vec()),
+            Err(crate::BencodeError::UnexpectedEof(_))
+        ));
+
+        let (_, errors) = Bencode::parse_collecting(b"ll5:abcee");
+        assert_eq!(errors.len(), 1);
+
+        let mut decoder = PartialDecoder::new();
+        decoder.push(b"ll5:abcee");
+        assert_eq!(decoder.try_decode(), Ok(None));
+        decoder.push(b"ee");
+        assert_eq!(
+            decoder.try_decode(),
+            Ok(Some(Bencode::list(vec![Bencode::list(vec![
+                Bencode::bytes("abcee")
+            ])])))
+        );
+    }
+
+    #[test]
+    fn test_serialize_chunks() {
+        let value =
+            Bencode::parse(b"d4:infod6:lengthi36e4:name4:spame4:listli1eli2e3:dogeee".to_vec())
+                .unwrap();
+        let chunks: Vec<Vec<u8>> = value.serialize_chunks().collect();
+        assert_eq!(chunks.concat(), value.serialize());
+        assert_eq!(chunks[0], b"d".to_vec());
+        assert_eq!(chunks[1], b"4:info".to_vec());
+        assert_eq!(chunks.len(), 17);
+    }
+
+    #[test]
+    fn test_announce_list() {
+        let torrent =
+            Bencode::parse(b"d13:announce-listll7:tracker6:backupel5:othereee".to_vec()).unwrap();
+        assert_eq!(
+            torrent.announce_list(),
+            Some(vec![
+                vec!["tracker".to_owned(), "backup".to_owned()],
+                vec!["other".to_owned()],
+            ])
+        );
+
+        let torrent = Bencode::parse(b"d13:announce-listl7:trackerl5:othereee".to_vec()).unwrap();
+        assert_eq!(torrent.announce_list(), None);
+        assert_eq!(Bencode::Integer(1).announce_list(), None);
+    }
+
+    #[test]
+    fn test_missing_terminator_is_eof() {
+        assert_eq!(
+            Bencode::parse(b"i13".to_vec()),
+            Err(crate::BencodeError::UnexpectedEof(
+                "Unexpected end of input at index 3".to_owned()
+            ))
+        );
+        assert_eq!(
+            Bencode::parse(b"3abc".to_vec()),
+            Err(crate::BencodeError::UnexpectedEof(
+                "Unexpected end of input at index 4".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_digits() {
+        assert_eq!(parse_digits(b"0"), Some(0));
+        assert_eq!(parse_digits(b"18446744073709551615"), Some(u64::MAX));
+        assert_eq!(parse_digits(b"18446744073709551616"), None);
+        assert_eq!(parse_digits(b""), None);
+        assert_eq!(parse_digits(b"1a"), None);
+    }
+
+    #[test]
+    fn test_parse_with_options() {
+        let source = b"d4:spami1e3:cati2e4:spami3ee".to_vec();
+        let lenient = ParseOptions::default();
         assert_eq!(
-            p.parse_list(),
-            Ok(Bencode::List(vec![
-                Bencode::Bytes(b"spam".to_vec()),
-                Bencode::Bytes(b"dog".to_vec()),
-            ]))
+            Bencode::parse_with_options(source.clone(), lenient),
+            Bencode::parse(source.clone())
+        );
+
+        let duplicates = ParseOptions {
+            check_duplicates: true,
+            ..lenient
+        };
+        assert_eq!(
+            Bencode::parse_with_options(source.clone(), duplicates),
+            Err(crate::BencodeError::Unexpected(
+                "Duplicate dict key at index 18".to_owned()
+            ))
+        );
+
+        let strict = ParseOptions {
+            check_canonical: true,
+            check_duplicates: true,
+            max_depth: 1,
+            max_bytes: 1024,
+            max_elements: 8,
+        };
+        assert_eq!(
+            Bencode::parse_with_options(source, strict),
+            Err(crate::BencodeError::Unexpected(
+                "Dict key out of order at index 10".to_owned()
+            ))
+        );
+        assert_eq!(
+            Bencode::parse_with_options(b"d3:cati2e3:cati3ee".to_vec(), strict),
+            Err(crate::BencodeError::Unexpected(
+                "Duplicate dict key at index 9".to_owned()
+            ))
         );
+        assert!(Bencode::parse_with_options(b"d3:cati2e4:spami1ee".to_vec(), strict).is_ok());
+        assert!(matches!(
+            Bencode::parse_with_options(b"d3:catlee".to_vec(), strict),
+            Err(crate::BencodeError::LimitExceeded(_))
+        ));
     }
+
     #[test]
-    fn test_parse_list_empty() {
-        let mut p = Parser::new(b"le");
-        assert_eq!(p.parse_list(), Ok(Bencode::List(vec![])));
+    fn test_default() {
+        let mut root = Bencode::default();
+        assert_eq!(root, Bencode::Dict(DictMap::new()));
+        assert_eq!(root.serialize(), b"de".to_vec());
+
+        let taken = std::mem::take(&mut root);
+        assert_eq!(taken, Bencode::default());
     }
+
     #[test]
-    fn test_parse_dict() {
-        let mut p = Parser::new(b"d4:spam3:dog3:cati36ee");
+    fn test_total_length() {
+        let single = Bencode::parse(b"d4:infod6:lengthi1024e4:name4:spamee".to_vec()).unwrap();
+        assert_eq!(single.total_length(), Some(1024));
+
+        let multi = Bencode::parse(
+            b"d4:infod5:filesld6:lengthi100e4:pathl1:aeed6:lengthi20e4:pathl1:beed6:lengthi3e4:pathl1:ceee4:name4:spamee"
+                .to_vec(),
+        )
+        .unwrap();
+        assert_eq!(multi.total_length(), Some(123));
+
+        let neither = Bencode::parse(b"d4:infod4:name4:spamee".to_vec()).unwrap();
+        assert_eq!(neither.total_length(), None);
+        assert_eq!(Bencode::Integer(1).total_length(), None);
+    }
+
+    #[test]
+    fn test_integer_without_digits() {
         assert_eq!(
-            p.parse_dict(),
-            Ok(Bencode::Dict(BTreeMap::from([
-                (b"spam".to_vec(), Bencode::Bytes(b"dog".to_vec())),
-                (b"cat".to_vec(), Bencode::Integer(36)),
-            ])))
+            Bencode::parse(b"ie".to_vec()),
+            Err(crate::BencodeError::Unexpected(
+                "Empty integer at index 0".to_owned()
+            ))
+        );
+        assert_eq!(
+            Bencode::parse(b"li1ei-ee".to_vec()),
+            Err(crate::BencodeError::Unexpected(
+                "Sign without digits while parsing integer at index 4".to_owned()
+            ))
         );
     }
 
     #[test]
-    fn test_parse_dict_empty() {
-        let mut p = Parser::new(b"de");
-        assert_eq!(p.parse_dict(), Ok(Bencode::Dict(BTreeMap::new())));
+    fn test_entries() {
+        let dict = Bencode::parse(b"d4:spaml1:ae3:cati1e3:dog4:barke".to_vec()).unwrap();
+        let keys: Vec<&[u8]> = dict
+            .entries()
+            .unwrap()
+            .into_iter()
+            .map(|(k, _)| k)
+            .collect();
+        assert_eq!(keys, vec![b"cat".as_slice(), b"dog", b"spam"]);
+
+        let mut entries = dict.into_entries().unwrap();
+        let (key, value) = entries.pop().unwrap();
+        assert_eq!(key, b"spam".to_vec());
+        assert_eq!(value.into_list(), Some(vec![Bencode::bytes("a")]));
+        assert_eq!(entries[0], (b"cat".to_vec(), Bencode::Integer(1)));
+
+        assert_eq!(Bencode::Integer(1).entries(), None);
+        assert_eq!(Bencode::Integer(1).into_entries(), None);
+    }
+
+    #[test]
+    fn test_unterminated_containers() {
+        assert_eq!(
+            Bencode::parse(b"d3:fooi1e".to_vec()),
+            Err(crate::BencodeError::UnexpectedEof(
+                "Unexpected end of input at index 9".to_owned()
+            ))
+        );
+        assert_eq!(
+            Bencode::parse(b"li1e4:spam".to_vec()),
+            Err(crate::BencodeError::UnexpectedEof(
+                "Unexpected end of input at index 10".to_owned()
+            ))
+        );
+        assert_eq!(
+            Bencode::parse(b"ld3:fooi1ee".to_vec()),
+            Err(crate::BencodeError::UnexpectedEof(
+                "Unexpected end of input at index 11".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_variant_counts() {
+        let value = Bencode::parse(
+            b"d4:infod6:lengthi36e4:name4:spame4:listli1eli2e3:dogeded1:ai3eeee".to_vec(),
+        )
+        .unwrap();
+        assert_eq!(
+            value.variant_counts(),
+            VariantCounts {
+                bytes: 2,
+                integers: 4,
+                lists: 2,
+                dicts: 4,
+            }
+        );
+        assert_eq!(
+            Bencode::Integer(1).variant_counts(),
+            VariantCounts {
+                integers: 1,
+                ..VariantCounts::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_spanned() {
+        let source = b"d4:infoli1eli-20eee4:name4:spame";
+        let tree = Bencode::parse_spanned(source).unwrap();
+        assert_eq!(tree.span, 0..source.len());
+
+        let entries = match tree.value {
+            SpannedValue::Dict(entries) => entries,
+            _ => panic!("expected a dict"),
+        };
+        assert_eq!(entries[0].0, b"info".to_vec());
+        assert_eq!(entries[1].0, b"name".to_vec());
+        assert_eq!(&source[entries[1].1.span.clone()], b"4:spam");
+
+        let info = &entries[0].1;
+        assert_eq!(&source[info.span.clone()], b"li1eli-20eee");
+        let items = match &info.value {
+            SpannedValue::List(items) => items,
+            _ => panic!("expected a list"),
+        };
+        assert_eq!(items[0].span, 8..11);
+        assert_eq!(items[0].value, SpannedValue::Integer(1));
+
+        let nested = match &items[1].value {
+            SpannedValue::List(nested) => nested,
+            _ => panic!("expected a list"),
+        };
+        assert_eq!(
+            nested[0],
+            SpannedBencode {
+                value: SpannedValue::Integer(-20),
+                span: 12..17,
+            }
+        );
+        assert!(items[1].span.start <= nested[0].span.start);
+        assert!(nested[0].span.end <= items[1].span.end);
+
+        assert!(Bencode::parse_spanned(b"li1e").is_err());
+        assert!(Bencode::parse_spanned(b"i1ei2e").is_err());
+        assert!(Bencode::parse_spanned(b"").is_err());
+
+        let unsorted = Bencode::parse_spanned(b"d1:bi1e1:ai2e1:bi3ee").unwrap();
+        assert_eq!(
+            unsorted.value,
+            SpannedValue::Dict(vec![
+                (b"b".to_vec(), spanned_integer(1, 4..7)),
+                (b"a".to_vec(), spanned_integer(2, 10..13)),
+                (b"b".to_vec(), spanned_integer(3, 16..19)),
+            ])
+        );
+        let invalid: [&[u8]; 3] = [b"d1:ali1e5:spamee", b"li1ei01ee", b"d1:ai1e2:bi2ee"];
+        for source in invalid {
+            assert_eq!(
+                Bencode::parse_spanned(source).err(),
+                Bencode::parse(source.to_vec()).err()
+            );
+        }
+    }
+
+    fn spanned_integer(int: i64, span: core::ops::Range<usize>) -> SpannedBencode {
+        return SpannedBencode {
+            value: SpannedValue::Integer(int),
+            span,
+        };
+    }
+
+    #[test]
+    fn test_serialize_dict_from() {
+        let dict = Bencode::dict([
+            ("cat", Bencode::integer(1)),
+            ("dog", Bencode::list(vec![Bencode::bytes("bark")])),
+            ("spam", Bencode::bytes("eggs")),
+        ]);
+        let sorted = dict.entries().unwrap();
+        assert_eq!(Bencode::serialize_dict_from(sorted), Ok(dict.serialize()));
+        assert_eq!(Bencode::serialize_dict_from([]), Ok(b"de".to_vec()));
+
+        let one = Bencode::integer(1);
+        assert_eq!(
+            Bencode::serialize_dict_from([(b"spam".as_slice(), &one), (b"cat", &one)]),
+            Err(crate::BencodeError::Unexpected(
+                "Dict key \"cat\" out of order at entry 1".to_owned()
+            ))
+        );
+        assert_eq!(
+            Bencode::serialize_dict_from([(b"cat".as_slice(), &one), (b"cat", &one)]),
+            Err(crate::BencodeError::Unexpected(
+                "Duplicate dict key \"cat\" at entry 1".to_owned()
+            ))
+        );
+    }
+
+    fn torrent_with_pieces(length: i64, piece_length: i64, pieces: usize) -> Bencode {
+        return Bencode::dict([(
+            "info",
+            Bencode::dict([
+                ("length", Bencode::integer(length)),
+                ("piece length", Bencode::integer(piece_length)),
+                ("pieces", Bencode::bytes(vec![0; pieces])),
+            ]),
+        )]);
+    }
+
+    #[test]
+    fn test_validate_pieces() {
+        assert_eq!(torrent_with_pieces(100, 32, 80).validate_pieces(), Ok(4));
+        assert_eq!(torrent_with_pieces(96, 32, 60).validate_pieces(), Ok(3));
+        assert_eq!(
+            torrent_with_pieces(100, 32, 70).validate_pieces(),
+            Err(crate::BencodeError::Unexpected(
+                "Pieces length 70 is not a multiple of 20".to_owned()
+            ))
+        );
+        assert_eq!(
+            torrent_with_pieces(100, 32, 60).validate_pieces(),
+            Err(crate::BencodeError::Unexpected(
+                "Expected 4 pieces for a total length of 100 but found 3".to_owned()
+            ))
+        );
+        assert_eq!(
+            torrent_with_pieces(100, 0, 80).validate_pieces(),
+            Err(crate::BencodeError::Unexpected(
+                "Invalid piece length 0".to_owned()
+            ))
+        );
+
+        let no_pieces = Bencode::dict([("info", Bencode::dict([("length", Bencode::integer(1))]))]);
+        assert_eq!(
+            no_pieces.validate_pieces(),
+            Err(crate::BencodeError::Unexpected(
+                "Missing pieces field".to_owned()
+            ))
+        );
+        let wrong_type =
+            Bencode::dict([("info", Bencode::dict([("pieces", Bencode::integer(1))]))]);
+        assert_eq!(
+            wrong_type.validate_pieces(),
+            Err(crate::BencodeError::Unexpected(
+                "Pieces field is not a byte string".to_owned()
+            ))
+        );
+        assert_eq!(
+            Bencode::dict([("info", Bencode::integer(1))]).validate_pieces(),
+            Err(crate::BencodeError::Unexpected(
+                "Info field is not a Dict".to_owned()
+            ))
+        );
+        assert_eq!(
+            torrent_with_pieces(-5, 32, 0).validate_pieces(),
+            Err(crate::BencodeError::Unexpected(
+                "Negative total length -5".to_owned()
+            ))
+        );
+
+        let files = Bencode::list(vec![
+            Bencode::dict([("length", Bencode::integer(70))]),
+            Bencode::dict([("length", Bencode::integer(-6))]),
+        ]);
+        let multi_file = Bencode::dict([(
+            "info",
+            Bencode::dict([
+                ("files", files),
+                ("piece length", Bencode::integer(32)),
+                ("pieces", Bencode::bytes(vec![0; 40])),
+            ]),
+        )]);
+        assert_eq!(
+            multi_file.validate_pieces(),
+            Err(crate::BencodeError::Unexpected(
+                "Negative length -6 for file 1".to_owned()
+            ))
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_hash_into() {
+        struct Chunks(Vec<Vec<u8>>);
+
+        impl std::io::Write for Chunks {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.push(buf.to_vec());
+                return Ok(buf.len());
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                return Ok(());
+            }
+        }
+
+        let torrent = Bencode::parse(
+            b"d8:announce3:url4:infod5:filesld6:lengthi-3e4:pathl1:aeee4:name4:spamee".to_vec(),
+        )
+        .unwrap();
+        let info = torrent.get(b"info").unwrap();
+        let mut hasher = Chunks(vec![]);
+        info.hash_into(&mut hasher).unwrap();
+        assert!(hasher.0.len() > 1);
+        assert_eq!(hasher.0.concat(), info.serialize());
+
+        let mut out = vec![];
+        torrent.serialize_to(&mut out).unwrap();
+        assert_eq!(out, torrent.serialize());
+    }
+
+    #[test]
+    fn test_is_valid() {
+        assert!(Bencode::is_valid(b"i0e"));
+        assert!(Bencode::is_valid(b"0:"));
+        assert!(Bencode::is_valid(b"d4:spamli1e3:dogee"));
+        assert!(Bencode::is_valid(b"d4:spami1e3:cati2ee"));
+
+        assert!(!Bencode::is_valid(b"i1ei2e"));
+        assert!(!Bencode::is_valid(b"i01e"));
+        for input in MALFORMED_INPUTS {
+            assert!(!Bencode::is_valid(input), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_get_mut_and_insert() {
+        let mut torrent =
+            Bencode::parse(b"d8:announce7:old-url4:infod6:lengthi3eee".to_vec()).unwrap();
+        if let Some(announce) = torrent.get_mut(b"announce") {
+            *announce = Bencode::bytes("new-url");
+        }
+        if let Some(info) = torrent.get_mut(b"info") {
+            assert_eq!(info.insert(key("name"), Bencode::bytes("spam")), None);
+            assert_eq!(
+                info.insert(key("length"), Bencode::integer(4)),
+                Some(Bencode::Integer(3))
+            );
+        }
+        assert_eq!(
+            torrent.serialize(),
+            b"d8:announce7:new-url4:infod6:lengthi4e4:name4:spamee".to_vec()
+        );
+
+        let mut int = Bencode::Integer(1);
+        assert_eq!(int.get_mut(b"spam"), None);
+        assert_eq!(int.insert(key("spam"), Bencode::integer(2)), None);
+        assert_eq!(int, Bencode::Integer(1));
+        assert_eq!(torrent.get_mut(b"missing"), None);
+    }
+
+    #[test]
+    fn test_dict_value_error_names_key() {
+        assert_eq!(
+            Bencode::parse(b"d8:announce3:url4:infod6:lengthi01eee".to_vec()),
+            Err(crate::BencodeError::Unexpected(
+                "Leading 0 while parsing integer at index 31 in value of key \"length\" in value of key \"info\"".to_owned()
+            ))
+        );
+        assert_eq!(
+            Bencode::parse(b"d4:spami1e".to_vec()),
+            Err(crate::BencodeError::UnexpectedEof(
+                "Unexpected end of input at index 10".to_owned()
+            ))
+        );
+        assert_eq!(
+            Bencode::parse(b"d1:\xffi-0ee".to_vec()),
+            Err(crate::BencodeError::Unexpected(
+                "Negative 0 while parsing integer at index 4".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_key_cmp() {
+        use std::cmp::Ordering;
+
+        assert_eq!(Bencode::key_cmp(b"a", b"ab"), Ordering::Less);
+        assert_eq!(Bencode::key_cmp(b"ab", b"a"), Ordering::Greater);
+        assert_eq!(Bencode::key_cmp(b"spam", b"spam"), Ordering::Equal);
+        assert_eq!(Bencode::key_cmp(b"", b"a"), Ordering::Less);
+        assert_eq!(Bencode::key_cmp(b"\xff", b"z"), Ordering::Greater);
+
+        let mut keys = vec![key("ab"), key("b"), key("a"), key("")];
+        keys.sort_by(|a, b| Bencode::key_cmp(a, b));
+        let dict = Bencode::dict(keys.iter().map(|k| (k.clone(), Bencode::integer(0))));
+        let dict_keys: Vec<Vec<u8>> = dict
+            .entries()
+            .unwrap()
+            .into_iter()
+            .map(|(k, _)| k.to_vec())
+            .collect();
+        assert_eq!(keys, dict_keys);
+    }
+
+    #[test]
+    fn test_as_utf8() {
+        let comment = Bencode::bytes("naïve comment");
+        assert_eq!(comment.as_utf8(), Some(Ok("naïve comment")));
+        assert_eq!(
+            comment.as_utf8_lossy(),
+            Some(std::borrow::Cow::Borrowed("naïve comment"))
+        );
+
+        let invalid = Bencode::bytes(b"spam\xffeggs".to_vec());
+        assert!(matches!(invalid.as_utf8(), Some(Err(_))));
+        assert_eq!(
+            invalid.as_utf8_lossy(),
+            Some(std::borrow::Cow::Owned::<str>(
+                "spam\u{fffd}eggs".to_owned()
+            ))
+        );
+
+        assert_eq!(Bencode::integer(1).as_utf8(), None);
+        assert_eq!(Bencode::list(vec![]).as_utf8_lossy(), None);
+    }
+
+    #[test]
+    fn test_reusable_decoder() {
+        let inputs: [&[u8]; 4] = [
+            b"d8:intervali1800e5:peers6:\x7f\0\0\x01\x1a\xe1e",
+            b"d14:failure reason6:bannede",
+            b"i1ei2e",
+            b"li1e4:spame",
+        ];
+        let mut decoder = ReusableDecoder::new();
+        for input in inputs {
+            decoder.reset(input);
+            assert_eq!(decoder.decode(), Bencode::parse(input.to_vec()));
+        }
+
+        let strict = ParseOptions {
+            check_canonical: true,
+            ..ParseOptions::default()
+        };
+        let mut decoder = ReusableDecoder::with_options(strict);
+        decoder.reset(b"d4:spami1e3:cati2ee");
+        assert!(decoder.decode().is_err());
+        decoder.reset(b"d3:cati2e4:spami1ee");
+        assert!(decoder.decode().is_ok());
+
+        let mut decoder = ReusableDecoder::new();
+        for i in 0..3 {
+            let response = Bencode::dict([("interval", Bencode::integer(i))]).serialize();
+            decoder.reset(&response);
+            drop(response);
+            assert_eq!(
+                decoder.decode(),
+                Ok(Bencode::dict([("interval", Bencode::integer(i))]))
+            );
+        }
+    }
+
+    #[test]
+    fn test_info_bytes() {
+        let torrent = Bencode::parse(
+            b"d8:announce3:url4:infod6:pieces0:4:name4:spam12:piece lengthi16eee".to_vec(),
+        )
+        .unwrap();
+        let bytes = torrent.info_bytes().unwrap();
+        assert_eq!(
+            bytes,
+            b"d4:name4:spam12:piece lengthi16e6:pieces0:e".to_vec()
+        );
+        assert_eq!(
+            Bencode::parse(bytes).as_ref(),
+            Ok(torrent.get(b"info").unwrap())
+        );
+
+        let not_dict = Bencode::parse(b"d4:info4:spame".to_vec()).unwrap();
+        assert_eq!(not_dict.info_bytes(), None);
+        assert_eq!(Bencode::default().info_bytes(), None);
+    }
+
+    #[test]
+    fn test_try_from() {
+        fn decode<T: TryInto<Bencode, Error = crate::BencodeError>>(
+            source: T,
+        ) -> Result<Bencode, crate::BencodeError> {
+            return source.try_into();
+        }
+
+        let expected = Ok(Bencode::list(vec![
+            Bencode::integer(1),
+            Bencode::bytes("spam"),
+        ]));
+        assert_eq!(Bencode::try_from(b"li1e4:spame".to_vec()), expected);
+        assert_eq!(Bencode::try_from(b"li1e4:spame".as_slice()), expected);
+        assert_eq!(decode(b"li1e4:spame".as_slice()), expected);
+        assert_eq!(
+            decode(b"li1e4:spamei2e".to_vec()),
+            Err(crate::BencodeError::TrailingData(
+                "Trailing data after value at index 11".to_owned()
+            ))
+        );
+        assert!(Bencode::try_from(b"i01e".as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_parse_with_stats() {
+        let node = std::mem::size_of::<Bencode>();
+        let (value, stats) =
+            Bencode::parse_with_stats(b"d4:infod6:lengthi3e5:filesl1:a2:bceee").unwrap();
+        assert_eq!(
+            value,
+            Bencode::parse(b"d4:infod6:lengthi3e5:filesl1:a2:bceee".to_vec()).unwrap()
+        );
+        // Six values, plus the bytes of every key and byte string.
+        assert_eq!(
+            stats,
+            ParseStats {
+                max_depth: 3,
+                total_bytes: 6 * node + 4 + 6 + 5 + 1 + 2,
+                node_count: 6,
+            }
+        );
+
+        let (_, stats) = Bencode::parse_with_stats(b"i1e").unwrap();
+        assert_eq!(
+            stats,
+            ParseStats {
+                max_depth: 0,
+                total_bytes: node,
+                node_count: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_dict_map() {
+        let tree = BTreeMap::from([
+            (key("spam"), Bencode::integer(1)),
+            (key("cat"), Bencode::integer(2)),
+        ]);
+        let mut dict = DictMap::from(tree);
+        assert_eq!(dict.len(), 2);
+        assert!(dict.contains_key(b"cat"));
+        assert_eq!(dict.insert(key("dog"), Bencode::integer(3)), None);
+        assert_eq!(dict.remove(b"cat"), Some(Bencode::Integer(2)));
+        if let Some(value) = dict.get_mut(b"spam") {
+            *value = Bencode::integer(4);
+        }
+        dict.extend([(key("ant"), Bencode::integer(5))]);
+
+        let mut keys: Vec<&Vec<u8>> = dict.keys().collect();
+        keys.sort();
+        assert_eq!(keys, vec![&key("ant"), &key("dog"), &key("spam")]);
+        assert_eq!(dict.values().count(), 3);
+        assert_eq!((&dict).into_iter().count(), 3);
+
+        let tree: BTreeMap<Vec<u8>, Bencode> = dict.into();
+        assert_eq!(
+            tree,
+            BTreeMap::from([
+                (key("ant"), Bencode::integer(5)),
+                (key("dog"), Bencode::integer(3)),
+                (key("spam"), Bencode::integer(4)),
+            ])
+        );
+        let dict: DictMap = tree.into_iter().collect();
+        assert_eq!(
+            Bencode::Dict(dict).serialize(),
+            b"d3:anti5e3:dogi3e4:spami4ee".to_vec()
+        );
+    }
+
+    #[test]
+    fn test_dict_map_mut() {
+        let mut dict = DictMap::from([
+            (key("ant"), Bencode::integer(5)),
+            (key("dog"), Bencode::integer(3)),
+        ]);
+        for (_, value) in dict.iter_mut() {
+            if let Bencode::Integer(int) = value {
+                *int *= 10;
+            }
+        }
+        for value in dict.values_mut() {
+            if let Bencode::Integer(int) = value {
+                *int += 1;
+            }
+        }
+        assert_eq!(dict.get(b"ant"), Some(&Bencode::Integer(51)));
+        assert_eq!(dict.get(b"dog"), Some(&Bencode::Integer(31)));
+
+        dict.entry(key("cow")).or_insert(Bencode::integer(7));
+        dict.entry(key("cow"))
+            .and_modify(|value| *value = Bencode::integer(8))
+            .or_insert_with(|| Bencode::integer(9));
+        assert_eq!(dict.get(b"cow"), Some(&Bencode::Integer(8)));
+        dict.retain(|key, _| key.as_slice() != b"dog");
+        assert_eq!(
+            format!("{:?}", dict),
+            "{[97, 110, 116]: Integer(51), [99, 111, 119]: Integer(8)}"
+        );
     }
 }